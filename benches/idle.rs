@@ -0,0 +1,117 @@
+//! Measures the CPU time used by a host with connected plugins while idle.
+//!
+//! Run with `cargo bench --bench idle`. The number of plugins and the length
+//! of the measurement can be changed through `AZALEA_BENCH_PLUGINS` and
+//! `AZALEA_BENCH_SECONDS`.
+use std::{
+    env, fs, mem, process,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use azalea_applet::{
+    Application, Element, PluginEvent, RuntimeMessage, SOCKET_ENV, connection,
+    iced::futures::{StreamExt, executor::block_on},
+    run,
+    widgets::{serde_types::Theme, text::Text},
+};
+
+/// The share of a single core an idle panel may use.
+const MAX_USAGE: f64 = 0.01;
+
+struct Idle;
+
+impl Application for Idle {
+    type Message = ();
+
+    fn new() -> Self {
+        Idle
+    }
+
+    fn update(&mut self, _message: ()) {}
+
+    fn view(&self, _theme: &Theme) -> Element<()> {
+        Text::new("idle").into()
+    }
+}
+
+fn main() {
+    let plugins = setting("AZALEA_BENCH_PLUGINS", 20);
+    let seconds = setting("AZALEA_BENCH_SECONDS", 5);
+
+    let socket = env::temp_dir().join(format!("azalea-bench-{}.sock", process::id()));
+    // SAFETY: No other threads are running yet.
+    unsafe { env::set_var(SOCKET_ENV, &socket) };
+
+    let (connected, connections) = mpsc::channel();
+    thread::spawn(move || {
+        block_on(async move {
+            let mut messages = Box::pin(connection::subscribe());
+            // Plugins disconnect once the host drops them.
+            let mut hosted = Vec::new();
+            while let Some(message) = messages.next().await {
+                if let RuntimeMessage::New(plugin, _) = message {
+                    let _ = plugin.sender.send(PluginEvent::Update);
+                    hosted.push(plugin);
+                    let _ = connected.send(());
+                }
+            }
+        })
+    });
+
+    while !socket.exists() {
+        thread::sleep(Duration::from_millis(10));
+    }
+    for _ in 0..plugins {
+        thread::spawn(|| run(Idle::new()));
+    }
+    for _ in 0..plugins {
+        connections.recv().expect("host is running");
+    }
+    // Let the initial views settle.
+    thread::sleep(Duration::from_millis(500));
+
+    let start = Instant::now();
+    let before = cpu_time();
+    thread::sleep(Duration::from_secs(seconds));
+    let used = cpu_time() - before;
+    let elapsed = start.elapsed();
+
+    let usage = used.as_secs_f64() / elapsed.as_secs_f64();
+    println!(
+        "idle: {plugins} plugins used {:.2?} of CPU time in {:.2?} ({:.3}% of a core)",
+        used,
+        elapsed,
+        usage * 100.0
+    );
+
+    let _ = fs::remove_file(&socket);
+    if usage > MAX_USAGE {
+        eprintln!("idle: exceeded {:.1}% of a core", MAX_USAGE * 100.0);
+        process::exit(1);
+    }
+    process::exit(0);
+}
+
+fn setting(name: &str, default: u64) -> u64 {
+    env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+/// The CPU time used by the whole process so far.
+fn cpu_time() -> Duration {
+    // SAFETY: `getrusage` only writes to the given struct.
+    let usage = unsafe {
+        let mut usage: libc::rusage = mem::zeroed();
+        libc::getrusage(libc::RUSAGE_SELF, &mut usage);
+        usage
+    };
+    let time = |time: libc::timeval| {
+        Duration::from_secs(time.tv_sec as u64) + Duration::from_micros(time.tv_usec as u64)
+    };
+
+    time(usage.ru_utime) + time(usage.ru_stime)
+}
@@ -0,0 +1,513 @@
+//! A layer-shell panel hosting azalea plugins.
+//!
+//! The panel is configured through the file given as the first argument, or
+//! `azalea/config.toml` in the XDG config directory, see
+//! [`config`](azalea_applet::config). The first configured panel is shown
+//! on the output it names, or on every output if it names none, following
+//! outputs as they are plugged in and out. Every installed plugin is
+//! launched if none are configured.
+//!
+//! Popups of plugins are shown next to the widget they are anchored to, on
+//! the output the plugin was last used on. The surface grows by
+//! [`POPUP_HEIGHT`] while popups are open, without moving the windows next
+//! to the panel, and clicking beside a popup closes it.
+//!
+//! Changes to the config file and to the executables of the plugins are
+//! applied while the panel is running. Changing the output, anchor, height or
+//! margins of the panel needs a restart.
+use std::{collections::HashMap, env, path::PathBuf, process};
+
+use azalea_applet::{
+    RuntimeMessage,
+    config::{self, Anchor as Edge, Config, Panel as PanelConfig},
+    connection,
+    event::{self, Output},
+    runtime::{PluginRuntime, outputs, watch},
+    widgets::popup,
+};
+use iced::{
+    Alignment, Color, Element, Length, Rectangle, Size, Subscription, Task,
+    alignment::Horizontal,
+    mouse,
+    widget::{Space, column, container, mouse_area, opaque, row, stack},
+    window,
+};
+use iced_layershell::{
+    Appearance, MultiApplication,
+    reexport::{Anchor, KeyboardInteractivity, Layer},
+    settings::{LayerShellSettings, Settings, StartMode},
+    to_layer_message,
+};
+
+/// How far the surface grows to make room for popups, in logical pixels.
+const POPUP_HEIGHT: u32 = 480;
+
+fn main() -> Result<(), iced_layershell::Error> {
+    let path = env::args_os()
+        .nth(1)
+        .map(PathBuf::from)
+        .map(|path| std::path::absolute(&path).unwrap_or(path))
+        .or_else(|| config::default_path().filter(|path| path.exists()));
+    let config = match &path {
+        Some(path) => Config::load(path).unwrap_or_else(|error| {
+            eprintln!("azalea-panel: {error}");
+            process::exit(1);
+        }),
+        None => Config::default(),
+    };
+
+    let panel = config.panels.first().cloned().unwrap_or_default();
+    if config.panels.len() > 1 {
+        eprintln!(
+            "azalea-panel: only the first panel `{}` is shown",
+            panel.name
+        );
+    }
+
+    let [top, right, bottom, left] = panel.margin;
+    let edge = match panel.anchor {
+        Edge::Top => Anchor::Top,
+        Edge::Bottom => Anchor::Bottom,
+    };
+    Panel::run(Settings {
+        layer_settings: LayerShellSettings {
+            size: Some((0, panel.height)),
+            exclusive_zone: panel.exclusive_zone.unwrap_or(panel.height as i32),
+            anchor: edge | Anchor::Left | Anchor::Right,
+            margin: (top, right, bottom, left),
+            layer: Layer::Top,
+            keyboard_interactivity: KeyboardInteractivity::OnDemand,
+            // A surface is created for every output the panel is shown on,
+            // also for outputs plugged in later.
+            start_mode: match &panel.output {
+                Some(output) => StartMode::TargetScreen(output.clone()),
+                None => StartMode::AllScreens,
+            },
+            ..Default::default()
+        },
+        flags: (config, panel, path),
+        ..Default::default()
+    })
+}
+
+/// A surface showing the panel on an output.
+#[derive(Debug)]
+struct Surface {
+    id: window::Id,
+    /// The output the surface is on, once it is known.
+    output: Option<String>,
+    /// The size of the bar, which does not change when the surface grows
+    /// for popups.
+    size: Size,
+    /// Whether the surface has grown to show popups.
+    expanded: bool,
+}
+
+struct Panel {
+    runtime: PluginRuntime,
+    panel: PanelConfig,
+    theme: iced::Theme,
+    /// The config file, if the panel was started with one.
+    path: Option<PathBuf>,
+    /// The names of the outputs of the compositor.
+    outputs: Vec<String>,
+    /// The surfaces showing the panel, in the order they were opened.
+    surfaces: Vec<Surface>,
+    /// The surface plugins were last used on, which shows their popups.
+    active: Option<window::Id>,
+    /// The bounds of the widgets open popups are anchored to, by plugin id
+    /// and popup name.
+    anchors: HashMap<(usize, String), Rectangle>,
+}
+
+#[to_layer_message(multi)]
+#[derive(Debug, Clone)]
+enum Message {
+    Runtime(RuntimeMessage),
+    Event(window::Id, iced::Event),
+    Changed(PathBuf),
+    Outputs(Vec<String>),
+    Anchored(usize, String, Option<Rectangle>),
+    /// The surface was clicked beside the open popups.
+    Dismiss,
+}
+
+impl MultiApplication for Panel {
+    type Message = Message;
+    type Flags = (Config, PanelConfig, Option<PathBuf>);
+    type Theme = iced::Theme;
+    type Executor = iced::executor::Default;
+
+    fn new((config, panel, path): Self::Flags) -> (Self, Task<Message>) {
+        let mut runtime = PluginRuntime::new();
+        for error in runtime.discover() {
+            eprintln!("azalea-panel: {error}");
+        }
+        for error in runtime.configure(&config) {
+            eprintln!("azalea-panel: {error}");
+        }
+
+        (
+            Panel {
+                runtime,
+                panel,
+                theme: config.theme.to_iced(),
+                path,
+                outputs: Vec::new(),
+                surfaces: Vec::new(),
+                active: None,
+                anchors: HashMap::new(),
+            },
+            Task::none(),
+        )
+    }
+
+    fn namespace(&self) -> String {
+        String::from("azalea-panel")
+    }
+
+    fn remove_id(&mut self, id: window::Id) {
+        self.surfaces.retain(|surface| surface.id != id);
+        if self.active == Some(id) {
+            self.active = None;
+        }
+        self.assign_outputs();
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        let mut paths = self.runtime.executables();
+        paths.extend(self.path.clone());
+
+        Subscription::batch([
+            Subscription::run(connection::subscribe).map(Message::Runtime),
+            iced::event::listen_with(|event, _, id| Some(Message::Event(id, event))),
+            Subscription::run(outputs::watch).map(Message::Outputs),
+            Subscription::run_with_id(paths.clone(), watch::watch(paths)).map(Message::Changed),
+        ])
+    }
+
+    fn update(&mut self, message: Message) -> Task<Message> {
+        let task = match message {
+            Message::Runtime(message) => {
+                if let RuntimeMessage::Error(error, None) = &message {
+                    eprintln!("azalea-panel: {error}");
+                }
+                self.runtime
+                    .handle_plugin_message(message, self.theme.clone());
+                Task::none()
+            }
+            Message::Event(id, event) => {
+                self.handle_event(id, event);
+                Task::none()
+            }
+            Message::Outputs(outputs) => {
+                self.outputs = outputs;
+                self.assign_outputs();
+                Task::none()
+            }
+            Message::Changed(path) if self.path.as_ref() == Some(&path) => {
+                self.reload();
+                Task::none()
+            }
+            Message::Changed(path) => {
+                self.runtime.executable_changed(&path);
+                Task::none()
+            }
+            Message::Anchored(id, name, bounds) => {
+                match bounds {
+                    Some(bounds) => self.anchors.insert((id, name), bounds),
+                    None => self.anchors.remove(&(id, name)),
+                };
+                return Task::none();
+            }
+            Message::Dismiss => {
+                self.runtime.dismiss_popups();
+                Task::none()
+            }
+            _ => Task::none(),
+        };
+
+        Task::batch([task, self.sync_popups()])
+    }
+
+    fn view(&self, id: window::Id) -> Element<'_, Message> {
+        let panel = &self.panel;
+        let Some(surface) = self.surfaces.iter().find(|surface| surface.id == id) else {
+            return Space::new(Length::Fill, Length::Fill).into();
+        };
+
+        let bar = container(self.bar(surface.output.as_deref()))
+            .height(Length::Fixed(panel.height as f32))
+            .style(background);
+        if !surface.expanded {
+            return bar.into();
+        }
+
+        let outside = mouse_area(Space::new(Length::Fill, Length::Fill)).on_press(Message::Dismiss);
+        let base: Element<'_, Message> = match panel.anchor {
+            Edge::Top => column![bar, outside].into(),
+            Edge::Bottom => column![outside, bar].into(),
+        };
+
+        self.runtime
+            .popups()
+            .filter_map(|(plugin, popup)| {
+                let anchor = *self.anchors.get(&(plugin.id, popup.name.clone()))?;
+                let view = self.runtime.popup_view(plugin.id, &popup.name)?;
+                let view =
+                    opaque(container(Element::from(view).map(Message::Runtime)).style(background));
+                Some((anchor, view))
+            })
+            .fold(base, |base, (anchor, view)| match panel.anchor {
+                Edge::Top => popup::overlay(base, view, anchor),
+                Edge::Bottom => stack![
+                    base,
+                    column![
+                        Space::with_height(Length::Fill),
+                        row![Space::with_width(Length::Fixed(anchor.x)), view],
+                        Space::with_height(Length::Fixed(height(panel, true) as f32 - anchor.y)),
+                    ],
+                ]
+                .into(),
+            })
+    }
+
+    fn theme(&self) -> iced::Theme {
+        self.theme.clone()
+    }
+
+    fn style(&self, theme: &iced::Theme) -> Appearance {
+        // The bar and popups draw their own background, the space beside
+        // popups stays see-through.
+        Appearance {
+            background_color: Color::TRANSPARENT,
+            text_color: theme.palette().text,
+        }
+    }
+}
+
+impl Panel {
+    /// The plugins in the slots of the panel, with their views for the
+    /// output if it is known.
+    fn bar<'a>(&'a self, output: Option<&'a str>) -> Element<'a, Message> {
+        let panel = &self.panel;
+        let last = panel.slots.len().saturating_sub(1);
+
+        let slots = panel.slots.iter().enumerate().map(|(i, slot)| {
+            let align = match i {
+                0 => Horizontal::Left,
+                i if i == last => Horizontal::Right,
+                _ => Horizontal::Center,
+            };
+            let views = match output {
+                Some(output) => self.runtime.slot_views_on(slot, output),
+                None => self.runtime.slot_views(slot),
+            };
+            let views = row(views).spacing(panel.spacing).align_y(Alignment::Center);
+
+            container(Element::from(views).map(Message::Runtime))
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .align_x(align)
+                .align_y(Alignment::Center)
+                .into()
+        });
+
+        row(slots)
+            .spacing(panel.spacing)
+            .padding([0.0, panel.spacing])
+            .into()
+    }
+
+    /// The surface that is reported to plugins as the host surface: the one
+    /// on the first output, or the first opened one.
+    fn primary(&self) -> Option<window::Id> {
+        self.surfaces
+            .iter()
+            .min_by_key(|surface| {
+                let output = surface.output.as_ref();
+                output
+                    .and_then(|output| self.outputs.iter().position(|o| o == output))
+                    .unwrap_or(usize::MAX)
+            })
+            .map(|surface| surface.id)
+    }
+
+    fn handle_event(&mut self, id: window::Id, event: iced::Event) {
+        let event = match event {
+            iced::Event::Mouse(mouse::Event::CursorEntered | mouse::Event::ButtonPressed(_)) => {
+                self.active = Some(id);
+                event
+            }
+            iced::Event::Window(window::Event::Opened { size, .. }) => {
+                self.surfaces.push(Surface {
+                    id,
+                    output: None,
+                    size: Size::new(size.width, self.panel.height as f32),
+                    expanded: false,
+                });
+                self.assign_outputs();
+                event
+            }
+            iced::Event::Window(window::Event::Resized(size)) => {
+                let Some(surface) = self.surfaces.iter_mut().find(|surface| surface.id == id)
+                else {
+                    return;
+                };
+                surface.size.width = size.width;
+                let size = surface.size;
+                self.update_outputs();
+                if self.primary() != Some(id) {
+                    return;
+                }
+                iced::Event::Window(window::Event::Resized(size))
+            }
+            event => event,
+        };
+
+        self.runtime.handle_event(event);
+    }
+
+    /// Matches the surfaces to the outputs they are on and tells plugins
+    /// about them.
+    ///
+    /// iced_layershell does not say which output a surface was created for,
+    /// so surfaces without one take the outputs the panel is shown on in the
+    /// order both were announced, which is the order surfaces are created in.
+    fn assign_outputs(&mut self) {
+        let shown: Vec<&String> = self
+            .outputs
+            .iter()
+            .filter(|output| self.panel.output.as_ref().is_none_or(|o| o == *output))
+            .collect();
+        for surface in &mut self.surfaces {
+            if surface
+                .output
+                .as_ref()
+                .is_some_and(|output| !shown.contains(&output))
+            {
+                surface.output = None;
+            }
+        }
+
+        let mut free = shown
+            .into_iter()
+            .filter(|output| {
+                !self
+                    .surfaces
+                    .iter()
+                    .any(|surface| surface.output.as_ref() == Some(*output))
+            })
+            .cloned()
+            .collect::<Vec<String>>()
+            .into_iter();
+        for surface in self.surfaces.iter_mut().filter(|s| s.output.is_none()) {
+            surface.output = free.next();
+        }
+
+        self.update_outputs();
+    }
+
+    /// Tells plugins about the outputs that show the panel, with the size of
+    /// the panel on each.
+    fn update_outputs(&mut self) {
+        let outputs = self
+            .outputs
+            .iter()
+            .filter_map(|name| {
+                let surface = self
+                    .surfaces
+                    .iter()
+                    .find(|surface| surface.output.as_ref() == Some(name))?;
+                Some(Output {
+                    name: name.clone(),
+                    surface: event::Surface {
+                        width: surface.size.width,
+                        height: surface.size.height,
+                        ..Default::default()
+                    },
+                })
+            })
+            .collect();
+
+        self.runtime.set_outputs(outputs);
+    }
+
+    /// Grows the surface popups are shown on to fit them, shrinks the others
+    /// and looks up the widgets popups are anchored to, which may have moved.
+    fn sync_popups(&mut self) -> Task<Message> {
+        let open: Vec<(usize, String)> = self
+            .runtime
+            .popups()
+            .map(|(plugin, popup)| (plugin.id, popup.name.clone()))
+            .collect();
+        self.anchors.retain(|key, _| open.contains(key));
+
+        let target = self
+            .active
+            .filter(|id| self.surfaces.iter().any(|surface| surface.id == *id))
+            .or_else(|| self.primary());
+        let panel = &self.panel;
+        let resizes: Vec<Task<Message>> = self
+            .surfaces
+            .iter_mut()
+            .filter_map(|surface| {
+                let expanded = !open.is_empty() && target == Some(surface.id);
+                if surface.expanded == expanded {
+                    return None;
+                }
+                surface.expanded = expanded;
+                Some(Task::done(Message::SizeChange {
+                    id: surface.id,
+                    size: (0, height(panel, expanded)),
+                }))
+            })
+            .collect();
+        let anchors = self.runtime.popups().map(|(plugin, popup)| {
+            let (id, name) = (plugin.id, popup.name.clone());
+            popup
+                .anchor_bounds()
+                .map(move |bounds| Message::Anchored(id, name.clone(), bounds))
+        });
+
+        Task::batch(anchors.chain(resizes))
+    }
+
+    /// Applies the config file again, keeping the current config if it is
+    /// invalid.
+    fn reload(&mut self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let config = match Config::load(path) {
+            Ok(config) => config,
+            Err(error) => {
+                eprintln!("azalea-panel: {error}");
+                return;
+            }
+        };
+
+        for error in self.runtime.configure(&config) {
+            eprintln!("azalea-panel: {error}");
+        }
+        if let Some(panel) = config.panels.first() {
+            self.panel.slots = panel.slots.clone();
+            self.panel.spacing = panel.spacing;
+        }
+        self.theme = config.theme.to_iced();
+    }
+}
+
+/// The height of the surface of a panel, including the room for popups if
+/// they are open.
+fn height(panel: &PanelConfig, expanded: bool) -> u32 {
+    match expanded {
+        true => panel.height + POPUP_HEIGHT,
+        false => panel.height,
+    }
+}
+
+/// The background of the bar and of popups.
+fn background(theme: &iced::Theme) -> container::Style {
+    container::Style::default().background(theme.palette().background)
+}
@@ -0,0 +1,670 @@
+//! The declarative configuration of a host.
+//!
+//! A configuration describes the panels of the host, the plugins it launches
+//! and the theme they are shown with:
+//!
+//! ```toml
+//! [theme]
+//! base = "Nord"
+//! accent = "#88c0d0"
+//! icon_theme = "Papirus"
+//!
+//! [[panel]]
+//! name = "top"
+//! anchor = "top"
+//! height = 32
+//! slots = ["start", "center", "end"]
+//!
+//! [[plugin]]
+//! name = "clock"
+//! slot = "end"
+//! args = ["--seconds"]
+//! env = { TZ = "Europe/Berlin" }
+//! settings = { format = "%H:%M" }
+//! ```
+//!
+//! Plugins are taken from their installed [manifests](crate::runtime::discovery)
+//! unless they name an `executable`, which is resolved against the directory
+//! of the configuration. Their `settings` are passed to them on startup and
+//! read with [`plugin_settings`].
+//!
+//! Errors point to the line of the offending value.
+use std::{
+    collections::{BTreeMap, HashSet},
+    env,
+    error::Error,
+    fmt, fs, io,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use iced::{
+    Color,
+    theme::{Palette, palette},
+};
+use serde::{Deserialize, de::DeserializeOwned};
+use toml::Spanned;
+
+use crate::{
+    runtime::{
+        discovery::Manifest,
+        layout::{Layout, Placement},
+        supervisor::Launch,
+    },
+    widgets::serde_types::Theme,
+};
+
+/// The environment variable holding the settings of a plugin, encoded as
+/// TOML.
+pub const SETTINGS_ENV: &str = "AZALEA_SETTINGS";
+
+/// Returns the settings the host configured for this plugin, if any.
+pub fn plugin_settings<T: DeserializeOwned>() -> Option<Result<T, toml::de::Error>> {
+    let settings = env::var(SETTINGS_ENV).ok()?;
+    Some(toml::from_str(&settings))
+}
+
+/// Returns the path of the configuration in the XDG config directory.
+pub fn default_path() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .map(|path| path.join("azalea").join("config.toml"))
+}
+
+/// The configuration of a host.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// The panels of the host, in order.
+    pub panels: Vec<Panel>,
+    /// The plugins the host launches, in order.
+    pub plugins: Vec<PluginConfig>,
+    /// The theme of the host and its plugins.
+    pub theme: ThemeConfig,
+    /// The file the configuration was read from.
+    pub path: PathBuf,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            panels: vec![Panel::default()],
+            plugins: Vec::new(),
+            theme: ThemeConfig::default(),
+            path: PathBuf::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Reads and validates the configuration at the given path.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path).map_err(|e| ConfigError::Io(path.into(), e))?;
+
+        Self::parse(&source, path)
+    }
+
+    /// Parses and validates a configuration read from the given path.
+    pub fn parse(source: &str, path: impl Into<PathBuf>) -> Result<Self, ConfigError> {
+        let path = path.into();
+        let invalid = |span: Option<Range<usize>>, reason: String| ConfigError::Invalid {
+            path: path.clone(),
+            position: span.map(|span| Position::of(source, span.start)),
+            reason,
+        };
+
+        let raw: RawConfig =
+            toml::from_str(source).map_err(|e| invalid(e.span(), e.message().to_string()))?;
+
+        let mut panels = Vec::new();
+        let mut panel_names = HashSet::new();
+        let mut slots = HashSet::new();
+        for panel in raw.panels {
+            if !panel_names.insert(panel.name.get_ref().clone()) {
+                return Err(invalid(
+                    Some(panel.name.span()),
+                    format!("duplicate panel `{}`", panel.name.get_ref()),
+                ));
+            }
+            if *panel.height.get_ref() == 0 {
+                return Err(invalid(
+                    Some(panel.height.span()),
+                    String::from("`height` must be positive"),
+                ));
+            }
+            let panel_slots = panel.slots.unwrap_or_else(|| {
+                Layout::default()
+                    .slots()
+                    .iter()
+                    .map(|slot| Spanned::new(0..0, slot.clone()))
+                    .collect()
+            });
+            for slot in &panel_slots {
+                if !slots.insert(slot.get_ref().clone()) {
+                    return Err(invalid(
+                        Some(slot.span()),
+                        format!("slot `{}` is used by more than one panel", slot.get_ref()),
+                    ));
+                }
+            }
+
+            panels.push(Panel {
+                name: panel.name.into_inner(),
+                output: panel.output,
+                anchor: panel.anchor,
+                height: panel.height.into_inner(),
+                exclusive_zone: panel.exclusive_zone,
+                margin: panel.margin,
+                spacing: panel.spacing,
+                slots: panel_slots.into_iter().map(Spanned::into_inner).collect(),
+            });
+        }
+        if panels.is_empty() {
+            panels.push(Panel::default());
+            slots.extend(Layout::default().slots().iter().cloned());
+        }
+
+        let directory = path.parent().unwrap_or(Path::new(""));
+        let mut plugins = Vec::new();
+        let mut plugin_names = HashSet::new();
+        for plugin in raw.plugins {
+            let name = plugin.name.get_ref();
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+            {
+                return Err(invalid(
+                    Some(plugin.name.span()),
+                    String::from(
+                        "`name` must be non-empty and only contain ASCII letters, digits, `-`, `_` and `.`",
+                    ),
+                ));
+            }
+            if !plugin_names.insert(name.clone()) {
+                return Err(invalid(
+                    Some(plugin.name.span()),
+                    format!("duplicate plugin `{name}`"),
+                ));
+            }
+            if let Some(slot) = &plugin.slot
+                && !slots.contains(slot.get_ref())
+            {
+                return Err(invalid(
+                    Some(slot.span()),
+                    format!("no panel has a slot `{}`", slot.get_ref()),
+                ));
+            }
+
+            plugins.push(PluginConfig {
+                position: Some(Position::of(source, plugin.name.span().start)),
+                name: plugin.name.into_inner(),
+                executable: plugin.executable.map(|executable| directory.join(executable)),
+                args: plugin.args,
+                env: plugin.env,
+                placement: plugin
+                    .slot
+                    .map(|slot| Placement::new(slot.into_inner(), plugin.priority)),
+                settings: plugin.settings,
+            });
+        }
+
+        Ok(Config {
+            panels,
+            plugins,
+            theme: raw.theme,
+            path,
+        })
+    }
+
+    /// Returns the slots of all panels, in order.
+    pub fn layout(&self) -> Layout {
+        Layout::new(self.panels.iter().flat_map(|panel| panel.slots.iter()))
+    }
+
+    /// Returns the panel with the given name.
+    pub fn panel(&self, name: &str) -> Option<&Panel> {
+        self.panels.iter().find(|panel| panel.name == name)
+    }
+
+    /// Returns an error about a configured plugin, pointing to its entry.
+    pub fn plugin_error(&self, plugin: &PluginConfig, reason: impl Into<String>) -> ConfigError {
+        ConfigError::Invalid {
+            path: self.path.clone(),
+            position: plugin.position,
+            reason: reason.into(),
+        }
+    }
+}
+
+/// A panel of the host.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Panel {
+    /// The unique name of the panel.
+    pub name: String,
+    /// The output the panel is shown on, or all outputs if `None`.
+    pub output: Option<String>,
+    /// The edge of the output the panel is attached to.
+    pub anchor: Anchor,
+    /// The height of the panel, in logical pixels.
+    pub height: u32,
+    /// The space reserved for the panel, its height if `None`.
+    pub exclusive_zone: Option<i32>,
+    /// The margins around the panel: top, right, bottom and left.
+    pub margin: [i32; 4],
+    /// The space between slots and plugins.
+    pub spacing: f32,
+    /// The slots of the panel, in order.
+    pub slots: Vec<String>,
+}
+
+impl Default for Panel {
+    fn default() -> Self {
+        Self {
+            name: String::from("main"),
+            output: None,
+            anchor: Anchor::Top,
+            height: 32,
+            exclusive_zone: None,
+            margin: [0; 4],
+            spacing: 8.0,
+            slots: Layout::default().slots().to_vec(),
+        }
+    }
+}
+
+/// The edge of an output a [`Panel`] is attached to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Anchor {
+    /// The top edge.
+    #[default]
+    Top,
+    /// The bottom edge.
+    Bottom,
+}
+
+/// A plugin launched by the host.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PluginConfig {
+    /// The name of the plugin.
+    pub name: String,
+    /// The executable of the plugin, if it is not an installed plugin.
+    pub executable: Option<PathBuf>,
+    /// The arguments passed to the plugin.
+    pub args: Vec<String>,
+    /// Additional environment variables for the plugin.
+    pub env: BTreeMap<String, String>,
+    /// Where the plugin is placed, overriding its manifest.
+    pub placement: Option<Placement>,
+    /// The settings passed to the plugin, see [`plugin_settings`].
+    pub settings: Option<toml::Table>,
+    position: Option<Position>,
+}
+
+impl PluginConfig {
+    /// Returns how the plugin is launched, given its installed manifest.
+    ///
+    /// Returns `None` if the plugin names no executable and is not installed.
+    pub fn launch(&self, manifest: Option<&Manifest>) -> Option<Launch> {
+        let mut launch = match &self.executable {
+            Some(executable) => Launch::new(&self.name, executable),
+            None => manifest?.launch(),
+        };
+
+        launch.args.extend(self.args.iter().cloned());
+        launch
+            .env
+            .extend(self.env.iter().map(|(key, value)| (key.clone(), value.clone())));
+        if let Some(settings) = &self.settings
+            && let Ok(settings) = toml::to_string(settings)
+        {
+            launch.env.push((SETTINGS_ENV.to_string(), settings));
+        }
+
+        Some(launch)
+    }
+}
+
+/// The theme of the host, based on a built-in iced theme.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    /// The name of the built-in iced theme the theme is based on.
+    #[serde(deserialize_with = "built_in")]
+    pub base: Option<String>,
+    /// Overrides the background color.
+    #[serde(deserialize_with = "color")]
+    pub background: Option<Color>,
+    /// Overrides the text color.
+    #[serde(deserialize_with = "color")]
+    pub text: Option<Color>,
+    /// Overrides the primary color.
+    #[serde(deserialize_with = "color")]
+    pub primary: Option<Color>,
+    /// Overrides the success color.
+    #[serde(deserialize_with = "color")]
+    pub success: Option<Color>,
+    /// Overrides the danger color.
+    #[serde(deserialize_with = "color")]
+    pub danger: Option<Color>,
+    /// Overrides the accent color, see [`Theme::accent`].
+    #[serde(deserialize_with = "color")]
+    pub accent: Option<Color>,
+    /// Overrides the surface color, see [`Theme::surface`].
+    #[serde(deserialize_with = "color")]
+    pub surface: Option<Color>,
+    /// The freedesktop icon theme, see [`icons`](crate::icons).
+    pub icon_theme: Option<String>,
+}
+
+impl ThemeConfig {
+    /// Returns the iced theme of the host.
+    pub fn to_iced(&self) -> iced::Theme {
+        let base = self
+            .base
+            .as_ref()
+            .and_then(|name| {
+                iced::Theme::ALL
+                    .iter()
+                    .find(|theme| theme.to_string() == *name)
+            })
+            .cloned()
+            .unwrap_or_default();
+
+        let palette = base.palette();
+        let overridden = Palette {
+            background: self.background.unwrap_or(palette.background),
+            text: self.text.unwrap_or(palette.text),
+            primary: self.primary.unwrap_or(palette.primary),
+            success: self.success.unwrap_or(palette.success),
+            danger: self.danger.unwrap_or(palette.danger),
+        };
+
+        if overridden == palette {
+            base
+        } else {
+            iced::Theme::custom(base.to_string(), overridden)
+        }
+    }
+
+    /// Returns the theme sent to plugins.
+    pub fn to_theme(&self) -> Theme {
+        let mut theme = Theme::from(self.to_iced());
+        let text = theme.palette.text;
+        if let Some(accent) = self.accent {
+            theme = theme.with_accent(palette::Pair::new(accent, text));
+        }
+        if let Some(surface) = self.surface {
+            theme = theme.with_surface(palette::Background::new(surface, text));
+        }
+        theme
+    }
+}
+
+/// A line and column in a configuration file, both starting at 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    /// The line.
+    pub line: usize,
+    /// The column, in characters.
+    pub column: usize,
+}
+
+impl Position {
+    fn of(source: &str, offset: usize) -> Self {
+        let before = &source[..offset.min(source.len())];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+        Position {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+/// An error produced while loading a [`Config`].
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The configuration could not be read.
+    Io(PathBuf, io::Error),
+    /// The configuration is not valid TOML or has invalid values.
+    Invalid {
+        /// The configuration file.
+        path: PathBuf,
+        /// Where the offending value is, if known.
+        position: Option<Position>,
+        /// What is wrong with it.
+        reason: String,
+    },
+}
+
+impl ConfigError {
+    /// The configuration file the error occurred in.
+    pub fn path(&self) -> &Path {
+        match self {
+            ConfigError::Io(path, _) | ConfigError::Invalid { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, error) => write!(f, "{}: {error}", path.display()),
+            ConfigError::Invalid {
+                path,
+                position: Some(Position { line, column }),
+                reason,
+            } => write!(f, "{}:{line}:{column}: {reason}", path.display()),
+            ConfigError::Invalid {
+                path,
+                position: None,
+                reason,
+            } => write!(f, "{}: {reason}", path.display()),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::Io(_, error) => Some(error),
+            ConfigError::Invalid { .. } => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawConfig {
+    #[serde(default, rename = "panel")]
+    panels: Vec<RawPanel>,
+    #[serde(default, rename = "plugin")]
+    plugins: Vec<RawPlugin>,
+    #[serde(default)]
+    theme: ThemeConfig,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawPanel {
+    name: Spanned<String>,
+    output: Option<String>,
+    #[serde(default)]
+    anchor: Anchor,
+    #[serde(default = "default_height")]
+    height: Spanned<u32>,
+    exclusive_zone: Option<i32>,
+    #[serde(default)]
+    margin: [i32; 4],
+    #[serde(default = "default_spacing")]
+    spacing: f32,
+    slots: Option<Vec<Spanned<String>>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawPlugin {
+    name: Spanned<String>,
+    executable: Option<PathBuf>,
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+    slot: Option<Spanned<String>>,
+    #[serde(default)]
+    priority: i32,
+    settings: Option<toml::Table>,
+}
+
+fn default_height() -> Spanned<u32> {
+    Spanned::new(0..0, Panel::default().height)
+}
+
+fn default_spacing() -> f32 {
+    Panel::default().spacing
+}
+
+fn built_in<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let name = String::deserialize(deserializer)?;
+    if iced::Theme::ALL.iter().any(|theme| theme.to_string() == name) {
+        Ok(Some(name))
+    } else {
+        Err(serde::de::Error::custom(format!(
+            "unknown theme `{name}`, expected one of the built-in iced themes"
+        )))
+    }
+}
+
+fn color<'de, D>(deserializer: D) -> Result<Option<Color>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let color = String::deserialize(deserializer)?;
+    Color::parse(&color)
+        .map(Some)
+        .ok_or_else(|| serde::de::Error::custom(format!("invalid color `{color}`")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATH: &str = "/etc/azalea/config.toml";
+
+    /// The line and column of the error in the given configuration.
+    fn error_at(source: &str) -> (usize, usize) {
+        match Config::parse(source, PATH) {
+            Err(ConfigError::Invalid {
+                position: Some(Position { line, column }),
+                ..
+            }) => (line, column),
+            other => panic!("expected an error with a position, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn empty_config_shows_the_default_panel() {
+        let config = Config::parse("", PATH).unwrap();
+
+        assert_eq!(config.panels, vec![Panel::default()]);
+        assert!(config.plugins.is_empty());
+    }
+
+    #[test]
+    fn bad_toml_points_to_the_error() {
+        assert_eq!(error_at("[[panel]]\nname = \"top\"\nheight = \n"), (3, 10));
+    }
+
+    #[test]
+    fn unknown_theme_points_to_its_name() {
+        assert_eq!(error_at("[theme]\nbase = \"Nope\"\n"), (2, 8));
+    }
+
+    #[test]
+    fn invalid_color_points_to_the_color() {
+        assert_eq!(
+            error_at("[theme]\nbase = \"Nord\"\naccent = \"#xyz\"\n"),
+            (3, 10)
+        );
+    }
+
+    #[test]
+    fn duplicate_panel_points_to_the_second_name() {
+        let source = "[[panel]]\nname = \"top\"\n\n[[panel]]\nname = \"top\"\nslots = [\"bar\"]\n";
+        assert_eq!(error_at(source), (5, 8));
+    }
+
+    #[test]
+    fn zero_height_points_to_the_height() {
+        assert_eq!(error_at("[[panel]]\nname = \"top\"\nheight = 0\n"), (3, 10));
+    }
+
+    #[test]
+    fn duplicate_plugin_points_to_the_second_name() {
+        let source = "[[plugin]]\nname = \"clock\"\n\n[[plugin]]\nname = \"clock\"\n";
+        assert_eq!(error_at(source), (5, 8));
+    }
+
+    #[test]
+    fn unknown_slot_points_to_the_slot() {
+        assert_eq!(
+            error_at("[[plugin]]\nname = \"clock\"\nslot = \"nowhere\"\n"),
+            (3, 8)
+        );
+    }
+
+    #[test]
+    fn bad_plugin_name_points_to_the_name() {
+        assert_eq!(error_at("[[plugin]]\nname = \"../clock\"\n"), (2, 8));
+        assert_eq!(error_at("[[plugin]]\n  name = \"\"\n"), (2, 10));
+    }
+
+    #[test]
+    fn launch_merges_args_env_and_settings() {
+        let source = r#"
+[[plugin]]
+name = "clock"
+args = ["--seconds"]
+env = { TZ = "UTC" }
+settings = { format = "%H:%M" }
+
+[[plugin]]
+name = "weather"
+executable = "bin/weather"
+"#;
+        let config = Config::parse(source, PATH).unwrap();
+        let manifest = Manifest {
+            name: String::from("clock"),
+            executable: PathBuf::from("/usr/lib/azalea/clock"),
+            version: String::from("1.0.0"),
+            icon: None,
+            permissions: Vec::new(),
+            placement: Placement::default(),
+            path: PathBuf::from("/usr/share/azalea/plugins/clock.toml"),
+        };
+
+        let mut expected = Launch::new("clock", "/usr/lib/azalea/clock").arg("--seconds");
+        expected.env = vec![
+            (String::from("TZ"), String::from("UTC")),
+            (
+                SETTINGS_ENV.to_string(),
+                String::from("format = \"%H:%M\"\n"),
+            ),
+        ];
+        assert_eq!(config.plugins[0].launch(Some(&manifest)), Some(expected));
+        assert_eq!(config.plugins[0].launch(None), None);
+
+        // Executables are resolved against the directory of the config.
+        assert_eq!(
+            config.plugins[1].launch(None),
+            Some(Launch::new("weather", "/etc/azalea/bin/weather"))
+        );
+    }
+}
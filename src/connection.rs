@@ -1,74 +1,228 @@
-use std::{error::Error, fs, path::Path, time::Duration};
+use std::{collections::HashMap, fs, pin::pin, time::Duration};
 
 use bytes::{Buf, BytesMut};
 use iced::{
-    futures::{SinkExt, Stream},
+    futures::{SinkExt, Stream, channel::mpsc::Sender},
     stream,
 };
 use serde::{Serialize, de::DeserializeOwned};
 use tokio::{
-    sync::mpsc::unbounded_channel,
+    sync::mpsc::{UnboundedSender, unbounded_channel},
     io::{AsyncReadExt, AsyncWriteExt},
     net::{UnixListener, UnixStream},
 };
 
-use crate::{RuntimeMessage, runtime::Plugin, tokio_runtime, unique_id};
+use crate::{
+    PluginEvent, PluginRequest, RuntimeMessage,
+    error::Error,
+    event::Interests,
+    runtime::{Plugin, layout::Placement},
+    socket_path, tokio_runtime, unique_id,
+};
+
+/// How the host checks that plugins are still responding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Heartbeat {
+    /// How long a connection may be idle before a heartbeat is sent.
+    pub interval: Duration,
+    /// How long a plugin may take to reply before it is reported as not
+    /// responding.
+    pub timeout: Duration,
+    /// How long a plugin may stay unresponsive before it is disconnected and
+    /// restarted if it is supervised, if at all.
+    pub restart_after: Option<Duration>,
+}
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(5),
+            timeout: Duration::from_secs(2),
+            restart_after: Some(Duration::from_secs(10)),
+        }
+    }
+}
 
 pub fn subscribe() -> impl Stream<Item = RuntimeMessage> {
-    stream::channel(100, |tx| async move {
+    subscribe_with(Heartbeat::default())
+}
+
+/// Like [`subscribe`], checking on plugins with the given [`Heartbeat`].
+pub fn subscribe_with(heartbeat: Heartbeat) -> impl Stream<Item = RuntimeMessage> {
+    stream::channel(100, move |tx| async move {
         let (sender, mut receiver) = unbounded_channel();
         let mut output = tx.clone();
         tokio_runtime().spawn(async move {
-            let path = Path::new("/tmp/sock");
+            let mut tx = tx;
+            let path = socket_path();
+            let path = path.as_path();
             if path.exists() {
-                fs::remove_file(path).unwrap();
+                // A stale socket that cannot be removed makes `bind` fail,
+                // which is reported below.
+                let _ = fs::remove_file(path);
             }
 
-            let listener = UnixListener::bind(path).unwrap();
+            let listener = match UnixListener::bind(path) {
+                Ok(listener) => listener,
+                Err(error) => {
+                    let error = Error::Bind(error.to_string());
+                    let _ = tx.send(RuntimeMessage::Error(error, None)).await;
+                    return;
+                }
+            };
             loop {
                 if let Ok((stream, _)) = listener.accept().await {
                     let sender = sender.clone();
                     let mut tx = tx.clone();
                     tokio_runtime().spawn(async move {
                         let id = unique_id();
+                        let pid = stream
+                            .peer_cred()
+                            .ok()
+                            .and_then(|cred| cred.pid())
+                            .map(|pid| pid as u32);
+
+                        let mut connection = Connection::new(stream);
+                        let (interests, widgets) = match connection.read_frame().await {
+                            Ok(Some(PluginRequest::Hello { interests, widgets })) => {
+                                (interests, widgets)
+                            }
+                            Ok(Some(PluginRequest::Subscribe(interests))) => (interests, Vec::new()),
+                            _ => (Interests::default(), Vec::new()),
+                        };
 
                         let (updates_sender, mut updates_receiver) = unbounded_channel();
                         let plugin = Plugin {
                             id,
                             sender: updates_sender,
                             view: None,
+                            revision: 0,
+                            interests,
+                            widgets,
+                            pid,
+                            process: None,
+                            error: None,
+                            responding: true,
+                            placement: Placement::default(),
+                            popups: Vec::new(),
+                            popup_errors: HashMap::new(),
+                            output_views: HashMap::new(),
+                            resyncing: false,
                         };
-                        tx.send(RuntimeMessage::New(plugin, id)).await.unwrap();
-                        let mut connection = Connection::new(stream);
+                        if tx.send(RuntimeMessage::New(Box::new(plugin), id)).await.is_err() {
+                            return;
+                        }
                         loop {
-                            if !connection.is_open().await {
-                                
-                                tx.send(RuntimeMessage::Shutdown(id)).await.unwrap();
-                                break;
-                            }
-                            connection.stream.flush().await.unwrap();
-                            if let Ok(Some(message)) = tokio::time::timeout(Duration::from_millis(500), updates_receiver.recv()).await {
-                                connection.write_frame(message).await.unwrap();
-                                if let Ok(Some(message)) = connection.read_frame().await {
-                                    sender.send((message, id)).unwrap();
+                            // Plugins write one reply per event and the
+                            // requests sent along with it, but they may
+                            // disconnect at any time.
+                            let frame = tokio::select! {
+                                event = updates_receiver.recv() => {
+                                    let Some(event) = event else {
+                                        break;
+                                    };
+                                    if let Err(error) = connection.write_frame(&event).await {
+                                        let _ = tx.send(RuntimeMessage::Error(error, Some(id))).await;
+                                        break;
+                                    }
+                                    reply(&mut connection, heartbeat, &mut tx, &sender, id).await
+                                }
+                                _ = tokio::time::sleep(heartbeat.interval) => {
+                                    if let Err(error) = connection.write_frame(&PluginEvent::Heartbeat).await {
+                                        let _ = tx.send(RuntimeMessage::Error(error, Some(id))).await;
+                                        break;
+                                    }
+                                    reply(&mut connection, heartbeat, &mut tx, &sender, id).await
+                                }
+                                frame = connection.read_frame() => frame,
+                            };
+
+                            match frame {
+                                Ok(Some(PluginRequest::Heartbeat)) => {}
+                                Ok(Some(message)) => {
+                                    if sender.send((message, id)).is_err() {
+                                        return;
+                                    }
+                                }
+                                Ok(None) => {}
+                                Err(error) => {
+                                    let fatal = error.is_fatal();
+                                    let _ = tx.send(RuntimeMessage::Error(error, Some(id))).await;
+                                    if fatal {
+                                        break;
+                                    }
                                 }
                             }
                         }
+
+                        let _ = tx.send(RuntimeMessage::Shutdown(id)).await;
                     });
                 }
             }
         });
         tokio_runtime().spawn(async move {
             while let Some((message, id)) = receiver.recv().await {
-                output
+                if output
                     .send(RuntimeMessage::Request(message, id))
                     .await
-                    .unwrap()
+                    .is_err()
+                {
+                    break;
+                }
             }
         });
     })
 }
 
+/// Waits for the reply of a plugin to an event, reporting it when it takes
+/// longer than the [`Heartbeat`] allows.
+///
+/// Plugins that are still not replying after [`Heartbeat::restart_after`]
+/// are disconnected with [`Error::Hung`]. Requests the plugin sends before
+/// its reply are forwarded to `sender` as they arrive.
+async fn reply(
+    connection: &mut Connection,
+    heartbeat: Heartbeat,
+    tx: &mut Sender<RuntimeMessage>,
+    sender: &UnboundedSender<(PluginRequest, usize)>,
+    id: usize,
+) -> Result<Option<PluginRequest>, Error> {
+    let frame = async {
+        loop {
+            match connection.read_frame::<PluginRequest>().await {
+                Ok(Some(request)) if !request.is_reply() => {
+                    let _ = sender.send((request, id));
+                }
+                Ok(None) => {}
+                frame => return frame,
+            }
+        }
+    };
+    let mut frame = pin!(frame);
+    if let Ok(frame) = tokio::time::timeout(heartbeat.timeout, &mut frame).await {
+        return frame;
+    }
+
+    let _ = tx.send(RuntimeMessage::Unresponsive(id)).await;
+    let frame = match heartbeat.restart_after {
+        Some(limit) => match tokio::time::timeout(limit, &mut frame).await {
+            Ok(frame) => frame,
+            Err(_) => {
+                // Supervised plugins are restarted, the others have to
+                // connect again once they recover.
+                let _ = tx.send(RuntimeMessage::Hung(id)).await;
+                return Err(Error::Hung);
+            }
+        },
+        None => frame.await,
+    };
+    if frame.is_ok() {
+        let _ = tx.send(RuntimeMessage::Responsive(id)).await;
+    }
+
+    frame
+}
+
 pub struct Connection {
     stream: UnixStream,
     buffer: BytesMut,
@@ -89,69 +243,169 @@ impl Connection {
         }
     }
 
-    pub async fn read_frame<T>(&mut self) -> Result<Option<T>, Box<dyn Error>>
+    /// Reads the next frame from the connection.
+    ///
+    /// Returns `None` for the empty frames written by [`Connection::is_open`].
+    /// This is cancel safe: partially received frames are kept in the buffer
+    /// until the next call.
+    pub async fn read_frame<T>(&mut self) -> Result<Option<T>, Error>
     where
-        T: Serialize + DeserializeOwned,
+        T: DeserializeOwned,
     {
         loop {
-            if let Ok(frame) = self.parse_frame().await {
+            if let Some(frame) = self.parse_frame()? {
                 return Ok(frame);
             }
 
             if 0 == self.stream.read_buf(&mut self.buffer).await? {
                 if self.buffer.is_empty() {
-                    return Ok(None);
+                    return Err(Error::Disconnected);
                 } else {
-                    return Err("connection reset by peer".into());
+                    return Err(Error::Io(String::from("connection reset by peer")));
                 }
             }
         }
     }
 
-    async fn parse_frame<T>(&mut self) -> Result<Option<T>, Box<dyn Error>>
+    /// Takes the first frame out of the buffer, if it was fully received.
+    fn parse_frame<T>(&mut self) -> Result<Option<Option<T>>, Error>
     where
-        T: Serialize + DeserializeOwned,
+        T: DeserializeOwned,
     {
-        //self.stream.readable().await?;
-        let mut size_buf = vec![0; 4];
-        let _ = self.stream.read_exact(&mut size_buf).await?;
-
-        let size = size_buf.as_slice().get_u32_le();
+        let Some(mut header) = self.buffer.get(..4) else {
+            return Ok(None);
+        };
+        let size = header.get_u32_le() as usize;
 
         if size == 0 {
-            let mut buf = vec![0u8; 1];
-            _ = self.stream.read(&mut buf).await?;
-            if buf.get(0).map(|b| b == &0).unwrap_or_default() {
+            if self.buffer.len() < 5 {
                 return Ok(None);
             }
+            self.buffer.advance(5);
+            return Ok(Some(None));
         }
 
-        if size as usize >= self.buffer.remaining() {
-            self.buffer.reserve(size as usize + 4);
-        }
-        let num_bytes = self.stream.read_buf(&mut self.buffer).await?;
-
-        if size != num_bytes as u32 {
-            return Err("Size doesn't match actual buffer size".into());
+        if self.buffer.len() < 4 + size {
+            self.buffer.reserve(4 + size - self.buffer.len());
+            return Ok(None);
         }
+        self.buffer.advance(4);
+        // The frame is consumed even if it cannot be decoded, so the
+        // connection stays usable.
+        let frame = self.buffer.split_to(size);
+        let (message, _) =
+            bincode::serde::decode_from_slice::<T, _>(&frame, bincode::config::standard())?;
 
-        let element: T =
-            bincode::serde::decode_from_slice(&self.buffer, bincode::config::standard())?.0;
-        self.buffer.advance(num_bytes);
-
-        Ok(Some(element))
+        Ok(Some(Some(message)))
     }
 
-    pub async fn write_frame<T>(&mut self, message: T) -> Result<(), Box<dyn Error>>
+    pub async fn write_frame<T>(&mut self, message: &T) -> Result<(), Error>
     where
-        T: Serialize + DeserializeOwned,
+        T: Serialize,
     {
         //self.stream.writable().await?;
         let mut src = bincode::serde::encode_to_vec(message, bincode::config::standard())?;
         let len = (src.len() as u32).to_le_bytes();
         src.splice(0..0, len);
-        self.stream.write_all(&mut src).await?;
+        self.stream.write_all(&src).await?;
         self.stream.flush().await?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use iced::futures::channel::mpsc;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn reply_forwards_fonts_sent_along_with_it() {
+        let (host, plugin) = UnixStream::pair().unwrap();
+        let (mut host, mut plugin) = (Connection::new(host), Connection::new(plugin));
+        plugin
+            .write_frame(&PluginRequest::Font(Vec::new().into()))
+            .await
+            .unwrap();
+        plugin.write_frame(&PluginRequest::Heartbeat).await.unwrap();
+
+        let (mut tx, _rx) = mpsc::channel(10);
+        let (sender, mut receiver) = unbounded_channel();
+        let frame = reply(&mut host, Heartbeat::default(), &mut tx, &sender, 7).await;
+
+        assert!(matches!(frame, Ok(Some(PluginRequest::Heartbeat))));
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok((PluginRequest::Font(_), 7))
+        ));
+    }
+
+    #[tokio::test]
+    async fn reply_forwards_popups_sent_along_with_it() {
+        let (host, plugin) = UnixStream::pair().unwrap();
+        let (mut host, mut plugin) = (Connection::new(host), Connection::new(plugin));
+        plugin
+            .write_frame(&PluginRequest::ClosePopup(String::from("menu")))
+            .await
+            .unwrap();
+        plugin.write_frame(&PluginRequest::Heartbeat).await.unwrap();
+
+        let (mut tx, _rx) = mpsc::channel(10);
+        let (sender, mut receiver) = unbounded_channel();
+        let frame = reply(&mut host, Heartbeat::default(), &mut tx, &sender, 7).await;
+
+        assert!(matches!(frame, Ok(Some(PluginRequest::Heartbeat))));
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok((PluginRequest::ClosePopup(name), 7)) if name == "menu"
+        ));
+    }
+
+    #[tokio::test]
+    async fn reply_forwards_output_views_sent_along_with_it() {
+        let (host, plugin) = UnixStream::pair().unwrap();
+        let (mut host, mut plugin) = (Connection::new(host), Connection::new(plugin));
+        plugin
+            .write_frame(&PluginRequest::OutputPatch(
+                String::from("DP-1"),
+                1,
+                Default::default(),
+            ))
+            .await
+            .unwrap();
+        plugin
+            .write_frame(&PluginRequest::Patch(1, Default::default()))
+            .await
+            .unwrap();
+
+        let (mut tx, _rx) = mpsc::channel(10);
+        let (sender, mut receiver) = unbounded_channel();
+        let frame = reply(&mut host, Heartbeat::default(), &mut tx, &sender, 7).await;
+
+        assert!(matches!(frame, Ok(Some(PluginRequest::Patch(1, _)))));
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok((PluginRequest::OutputPatch(output, 1, _), 7)) if output == "DP-1"
+        ));
+    }
+
+    #[tokio::test]
+    async fn hung_plugins_are_disconnected() {
+        let (host, _plugin) = UnixStream::pair().unwrap();
+        let mut host = Connection::new(host);
+        let heartbeat = Heartbeat {
+            interval: Duration::from_secs(5),
+            timeout: Duration::from_millis(10),
+            restart_after: Some(Duration::from_millis(10)),
+        };
+
+        let (mut tx, mut rx) = mpsc::channel(10);
+        let (sender, _receiver) = unbounded_channel();
+        let frame = reply(&mut host, heartbeat, &mut tx, &sender, 7).await;
+
+        assert!(matches!(frame, Err(Error::Hung)));
+        assert!(matches!(rx.try_next(), Ok(Some(RuntimeMessage::Unresponsive(7)))));
+        assert!(matches!(rx.try_next(), Ok(Some(RuntimeMessage::Hung(7)))));
+        assert!(rx.try_next().is_err());
+    }
+}
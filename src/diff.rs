@@ -0,0 +1,334 @@
+//! Incremental updates of view trees.
+//!
+//! Instead of resending its whole view after every update, a plugin sends
+//! the [`Patch`]es turning the previously sent tree into the new one. Nodes
+//! are addressed by their path of child indices from the root, following
+//! [`Widget::children`](crate::widgets::widget::Widget::children).
+use std::mem;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Element, error::Error, widgets::space::Space};
+
+/// A change to a view tree.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Patch {
+    /// Replaces the node at `path`, including its children.
+    Replace { path: Vec<usize>, element: Element },
+    /// Replaces the properties of the node at `path`, keeping its children.
+    ///
+    /// The children of `element` are placeholders and are discarded.
+    Update { path: Vec<usize>, element: Element },
+    /// Inserts a child into the list of the node at `path`.
+    Insert {
+        path: Vec<usize>,
+        index: usize,
+        element: Element,
+    },
+    /// Removes a child from the list of the node at `path`.
+    Remove { path: Vec<usize>, index: usize },
+}
+
+/// Computes the patches turning `old` into `new`.
+///
+/// `old` is only borrowed mutably to inspect its nodes without their
+/// children; it is left unchanged.
+pub fn diff(old: &mut Element, new: Element) -> Vec<Patch> {
+    let mut patches = Vec::new();
+    diff_node(old, new, &mut Vec::new(), &mut patches);
+    patches
+}
+
+/// Applies patches produced by [`diff`] to a tree.
+///
+/// Fails if a patch does not fit the tree, in which case the tree may be
+/// partially patched and should be replaced by a full view.
+pub fn apply(tree: &mut Element, patches: Vec<Patch>) -> Result<(), Error> {
+    for patch in patches {
+        match patch {
+            Patch::Replace { path, element } => *node_at(tree, &path)? = element,
+            Patch::Update { path, mut element } => {
+                let node = node_at(tree, &path)?;
+                if node.kind() != element.kind() {
+                    return Err(desync(&path, "node has a different type"));
+                }
+
+                match (
+                    node.as_widget_mut().child_list(),
+                    element.as_widget_mut().child_list(),
+                ) {
+                    (Some(old), Some(new)) => mem::swap(old, new),
+                    _ => {
+                        let old = node.as_widget_mut().children_mut();
+                        let new = element.as_widget_mut().children_mut();
+                        if old.len() != new.len() {
+                            return Err(desync(&path, "node has a different number of children"));
+                        }
+                        for (old, new) in old.into_iter().zip(new) {
+                            mem::swap(old, new);
+                        }
+                    }
+                }
+                *node = element;
+            }
+            Patch::Insert {
+                path,
+                index,
+                element,
+            } => {
+                let list = child_list(tree, &path)?;
+                if index > list.len() {
+                    return Err(desync(&path, "insertion index out of bounds"));
+                }
+                list.insert(index, element);
+            }
+            Patch::Remove { path, index } => {
+                let list = child_list(tree, &path)?;
+                if index >= list.len() {
+                    return Err(desync(&path, "removal index out of bounds"));
+                }
+                list.remove(index);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn diff_node(old: &mut Element, mut new: Element, path: &mut Vec<usize>, patches: &mut Vec<Patch>) {
+    if old.kind() != new.kind() {
+        patches.push(Patch::Replace {
+            path: path.clone(),
+            element: new,
+        });
+        return;
+    }
+
+    let old_children = take_children(old);
+    let new_children = take_children(&mut new);
+    let changed = match (encode(old), encode(&new)) {
+        (Ok(old), Ok(new)) => old != new,
+        _ => true,
+    };
+    restore_children(old, old_children);
+
+    if changed {
+        patches.push(Patch::Update {
+            path: path.clone(),
+            element: new,
+        });
+    }
+
+    match new_children {
+        Children::List(new_children) => {
+            let old_len = old.as_widget().children().len();
+            let new_len = new_children.len();
+
+            for (index, child) in new_children.into_iter().enumerate() {
+                if index < old_len {
+                    let old_child = old.as_widget_mut().children_mut().swap_remove(index);
+                    path.push(index);
+                    diff_node(old_child, child, path, patches);
+                    path.pop();
+                } else {
+                    patches.push(Patch::Insert {
+                        path: path.clone(),
+                        index,
+                        element: child,
+                    });
+                }
+            }
+            for index in (new_len..old_len).rev() {
+                patches.push(Patch::Remove {
+                    path: path.clone(),
+                    index,
+                });
+            }
+        }
+        Children::Fixed(new_children) => {
+            for (index, child) in new_children.into_iter().enumerate() {
+                if let Some(old_child) = old.as_widget_mut().children_mut().into_iter().nth(index) {
+                    path.push(index);
+                    diff_node(old_child, child, path, patches);
+                    path.pop();
+                }
+            }
+        }
+    }
+}
+
+enum Children {
+    List(Vec<Element>),
+    Fixed(Vec<Element>),
+}
+
+/// Takes the children out of a node, leaving placeholders behind.
+fn take_children(element: &mut Element) -> Children {
+    let widget = element.as_widget_mut();
+    if let Some(list) = widget.child_list() {
+        return Children::List(mem::take(list));
+    }
+
+    Children::Fixed(
+        widget
+            .children_mut()
+            .into_iter()
+            .map(|child| mem::replace(child, Space::new(0.0, 0.0).into()))
+            .collect(),
+    )
+}
+
+fn restore_children(element: &mut Element, children: Children) {
+    let widget = element.as_widget_mut();
+    match children {
+        Children::List(children) => {
+            if let Some(list) = widget.child_list() {
+                *list = children;
+            }
+        }
+        Children::Fixed(children) => {
+            for (slot, child) in widget.children_mut().into_iter().zip(children) {
+                *slot = child;
+            }
+        }
+    }
+}
+
+fn node_at<'a>(tree: &'a mut Element, path: &[usize]) -> Result<&'a mut Element, Error> {
+    let mut node = tree;
+    for (depth, &index) in path.iter().enumerate() {
+        node = node
+            .as_widget_mut()
+            .children_mut()
+            .into_iter()
+            .nth(index)
+            .ok_or_else(|| desync(&path[..=depth], "no such node"))?;
+    }
+    Ok(node)
+}
+
+fn child_list<'a>(tree: &'a mut Element, path: &[usize]) -> Result<&'a mut Vec<Element>, Error> {
+    node_at(tree, path)?
+        .as_widget_mut()
+        .child_list()
+        .ok_or_else(|| desync(path, "node has no list of children"))
+}
+
+fn encode(element: &Element) -> Result<Vec<u8>, bincode::error::EncodeError> {
+    bincode::serde::encode_to_vec(element, bincode::config::standard())
+}
+
+fn desync(path: &[usize], reason: &str) -> Error {
+    Error::Desync(format!("{reason} at {path:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::widgets::{column::Column, container::Container, text::Text};
+
+    fn column(children: impl IntoIterator<Item = Element>) -> Element {
+        Column::with_children(children).into()
+    }
+
+    fn text(fragment: &str) -> Element {
+        Text::new(fragment).into()
+    }
+
+    fn copy(element: &Element) -> Element {
+        let bytes = encode(element).unwrap();
+        bincode::serde::decode_from_slice(&bytes, bincode::config::standard())
+            .unwrap()
+            .0
+    }
+
+    fn kind(patch: &Patch) -> &'static str {
+        match patch {
+            Patch::Replace { .. } => "replace",
+            Patch::Update { .. } => "update",
+            Patch::Insert { .. } => "insert",
+            Patch::Remove { .. } => "remove",
+        }
+    }
+
+    /// Diffs `old` against `new`, checks that applying the patches to `old`
+    /// gives `new` and returns the kinds of the patches.
+    fn round_trip(mut old: Element, new: Element) -> Vec<&'static str> {
+        let before = encode(&old).unwrap();
+        let expected = encode(&new).unwrap();
+        let mut tree = copy(&old);
+
+        let patches = diff(&mut old, new);
+        assert_eq!(encode(&old).unwrap(), before, "diff changed the old tree");
+
+        let kinds = patches.iter().map(kind).collect();
+        apply(&mut tree, patches).unwrap();
+        assert_eq!(encode(&tree).unwrap(), expected);
+        kinds
+    }
+
+    #[test]
+    fn unchanged() {
+        let tree = || column([text("a"), text("b")]);
+        assert!(round_trip(tree(), tree()).is_empty());
+    }
+
+    #[test]
+    fn insert() {
+        let kinds = round_trip(
+            column([text("a")]),
+            column([text("a"), text("b"), text("c")]),
+        );
+        assert_eq!(kinds, ["insert", "insert"]);
+    }
+
+    #[test]
+    fn remove() {
+        let kinds = round_trip(column([text("a"), text("b"), text("c")]), column([text("a")]));
+        assert_eq!(kinds, ["remove", "remove"]);
+    }
+
+    #[test]
+    fn replace() {
+        let kinds = round_trip(
+            column([text("a")]),
+            column([Container::new(text("a")).into()]),
+        );
+        assert_eq!(kinds, ["replace"]);
+        assert_eq!(round_trip(text("a"), column([])), ["replace"]);
+    }
+
+    #[test]
+    fn nested_update() {
+        let kinds = round_trip(
+            column([Container::new(column([text("a"), text("b")])).into()]),
+            column([Container::new(column([text("a"), text("c")]))
+                .padding(4)
+                .into()]),
+        );
+        assert_eq!(kinds, ["update", "update"]);
+    }
+
+    #[test]
+    fn mismatched_patches_are_rejected() {
+        let mut tree = column([text("a")]);
+        let patches = vec![Patch::Remove {
+            path: Vec::new(),
+            index: 1,
+        }];
+        assert!(apply(&mut tree, patches).is_err());
+
+        let patches = vec![Patch::Update {
+            path: vec![0],
+            element: column([]),
+        }];
+        assert!(apply(&mut tree, patches).is_err());
+
+        let patches = vec![Patch::Insert {
+            path: vec![0],
+            index: 0,
+            element: text("b"),
+        }];
+        assert!(apply(&mut tree, patches).is_err());
+    }
+}
@@ -0,0 +1,70 @@
+//! Errors produced while talking to plugins.
+use std::{fmt, io};
+
+use serde::{Deserialize, Serialize};
+
+/// An error produced by the host or a plugin connection.
+///
+/// Errors only carry a description of their cause so they can be cloned into
+/// [`RuntimeMessage`](crate::RuntimeMessage)s.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Error {
+    /// The host socket could not be bound.
+    Bind(String),
+    /// Reading from or writing to the connection failed.
+    Io(String),
+    /// A frame could not be encoded or decoded.
+    Codec(String),
+    /// A widget of unknown type was received.
+    UnknownWidget(String),
+    /// A patch did not fit the view tree it was applied to.
+    Desync(String),
+    /// The plugin is no longer connected.
+    Disconnected,
+    /// The plugin stopped responding and was disconnected.
+    Hung,
+}
+
+impl Error {
+    /// Returns whether the connection the error occurred on is unusable.
+    pub fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            Error::Bind(_) | Error::Io(_) | Error::Disconnected | Error::Hung
+        )
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Bind(reason) => write!(f, "failed to bind the host socket: {reason}"),
+            Error::Io(reason) => write!(f, "connection error: {reason}"),
+            Error::Codec(reason) => write!(f, "malformed frame: {reason}"),
+            Error::UnknownWidget(name) => write!(f, "unknown widget `{name}`"),
+            Error::Desync(reason) => write!(f, "view out of sync: {reason}"),
+            Error::Disconnected => write!(f, "plugin disconnected"),
+            Error::Hung => write!(f, "plugin stopped responding"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(value: io::Error) -> Self {
+        Error::Io(value.to_string())
+    }
+}
+
+impl From<bincode::error::EncodeError> for Error {
+    fn from(value: bincode::error::EncodeError) -> Self {
+        Error::Codec(value.to_string())
+    }
+}
+
+impl From<bincode::error::DecodeError> for Error {
+    fn from(value: bincode::error::DecodeError) -> Self {
+        Error::Codec(value.to_string())
+    }
+}
@@ -0,0 +1,226 @@
+//! Input events the host forwards to plugins.
+//!
+//! Plugins only receive the events they declare interest in through
+//! [`Interests`], so applets that do not care about input are never woken up
+//! by it.
+use iced::keyboard;
+use serde::{Deserialize, Serialize};
+
+/// The kinds of input a plugin wants to be notified about.
+///
+/// By default a plugin is not interested in any input.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Interests {
+    /// Keyboard events while the plugin has focus, and focus changes.
+    pub keyboard: bool,
+    /// Pointer movement over the area of the plugin.
+    pub pointer: bool,
+    /// Size, scale factor and visibility changes of the host surface.
+    pub surface: bool,
+    /// The outputs the host shows panels on, with a view per output.
+    pub outputs: bool,
+}
+
+impl Interests {
+    /// Interest in every kind of input.
+    ///
+    /// Views per output are not input and stay off, see
+    /// [`Interests::outputs`].
+    pub fn all() -> Self {
+        Self {
+            keyboard: true,
+            pointer: true,
+            surface: true,
+            outputs: false,
+        }
+    }
+
+    /// Sets whether keyboard and focus events are delivered.
+    pub fn keyboard(mut self, keyboard: bool) -> Self {
+        self.keyboard = keyboard;
+        self
+    }
+
+    /// Sets whether pointer events are delivered.
+    pub fn pointer(mut self, pointer: bool) -> Self {
+        self.pointer = pointer;
+        self
+    }
+
+    /// Sets whether surface events are delivered.
+    pub fn surface(mut self, surface: bool) -> Self {
+        self.surface = surface;
+        self
+    }
+
+    /// Sets whether the plugin renders a view per output, see
+    /// [`Application::output_view`](crate::Application::output_view).
+    pub fn outputs(mut self, outputs: bool) -> Self {
+        self.outputs = outputs;
+        self
+    }
+}
+
+/// An input event delivered to a plugin.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Event {
+    /// A keyboard event, only delivered while the plugin has focus.
+    Keyboard(KeyboardEvent),
+    /// The pointer moved over the area of the plugin.
+    Pointer(PointerEvent),
+    /// The host surface changed size or scale factor.
+    Resized(Surface),
+    /// The plugin gained or lost keyboard focus.
+    Focus(bool),
+    /// The host surface was shown or hidden.
+    Visibility(bool),
+    /// The host closed the popup with the given name, see
+    /// [`popup`](crate::widgets::popup).
+    PopupDismissed(String),
+    /// The outputs the host shows panels on changed, e.g. because a monitor
+    /// was plugged in.
+    Outputs(Vec<Output>),
+}
+
+/// A keyboard event.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum KeyboardEvent {
+    /// A keyboard key was pressed.
+    KeyPressed {
+        /// The key pressed.
+        key: Key,
+        /// The key pressed with all keyboard modifiers applied, except Ctrl.
+        modified_key: Key,
+        /// The state of the modifier keys.
+        modifiers: Modifiers,
+        /// The text produced by the key press, if any.
+        text: Option<String>,
+    },
+    /// A keyboard key was released.
+    KeyReleased {
+        /// The key released.
+        key: Key,
+        /// The state of the modifier keys.
+        modifiers: Modifiers,
+    },
+    /// The keyboard modifiers have changed.
+    ModifiersChanged(Modifiers),
+}
+
+impl KeyboardEvent {
+    /// Converts an [`iced`] keyboard event, if it can be represented.
+    pub fn from_iced(event: keyboard::Event) -> Option<Self> {
+        match event {
+            keyboard::Event::KeyPressed {
+                key,
+                modified_key,
+                modifiers,
+                text,
+                ..
+            } => Some(Self::KeyPressed {
+                key: key.into(),
+                modified_key: modified_key.into(),
+                modifiers: modifiers.into(),
+                text: text.map(|text| text.to_string()),
+            }),
+            keyboard::Event::KeyReleased { key, modifiers, .. } => Some(Self::KeyReleased {
+                key: key.into(),
+                modifiers: modifiers.into(),
+            }),
+            keyboard::Event::ModifiersChanged(modifiers) => {
+                Some(Self::ModifiersChanged(modifiers.into()))
+            }
+        }
+    }
+}
+
+/// A key on the keyboard.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Key {
+    /// A named key, like `Enter` or `ArrowUp`.
+    Named(String),
+    /// A key string that corresponds to the character typed by the user.
+    Character(String),
+    /// An unidentified key.
+    Unidentified,
+}
+
+impl From<keyboard::Key> for Key {
+    fn from(value: keyboard::Key) -> Self {
+        match value {
+            keyboard::Key::Named(named) => Key::Named(format!("{named:?}")),
+            keyboard::Key::Character(character) => Key::Character(character.to_string()),
+            keyboard::Key::Unidentified => Key::Unidentified,
+        }
+    }
+}
+
+/// The current state of the keyboard modifiers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Modifiers {
+    /// Whether a shift key is pressed.
+    pub shift: bool,
+    /// Whether a control key is pressed.
+    pub control: bool,
+    /// Whether an alt key is pressed.
+    pub alt: bool,
+    /// Whether a logo key is pressed.
+    pub logo: bool,
+}
+
+impl From<keyboard::Modifiers> for Modifiers {
+    fn from(value: keyboard::Modifiers) -> Self {
+        Self {
+            shift: value.shift(),
+            control: value.control(),
+            alt: value.alt(),
+            logo: value.logo(),
+        }
+    }
+}
+
+/// A pointer event, relative to the area of the plugin.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PointerEvent {
+    /// The pointer entered the area of the plugin.
+    Entered,
+    /// The pointer moved to the given position.
+    Moved {
+        /// The horizontal position, in logical pixels.
+        x: f32,
+        /// The vertical position, in logical pixels.
+        y: f32,
+    },
+    /// The pointer left the area of the plugin.
+    Left,
+}
+
+/// The size and scale factor of the host surface.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Surface {
+    /// The width, in logical pixels.
+    pub width: f32,
+    /// The height, in logical pixels.
+    pub height: f32,
+    /// The scale factor of the output the surface is on.
+    pub scale_factor: f32,
+}
+
+/// An output, such as a monitor, the host shows a panel on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Output {
+    /// The name of the output, e.g. `DP-1`.
+    pub name: String,
+    /// The panel surface on the output.
+    pub surface: Surface,
+}
+
+impl Default for Surface {
+    fn default() -> Self {
+        Self {
+            width: 0.0,
+            height: 0.0,
+            scale_factor: 1.0,
+        }
+    }
+}
@@ -0,0 +1,550 @@
+//! Resolving freedesktop icon names to files.
+//!
+//! Icons are looked up in the current icon theme as described by the
+//! [icon theme specification], falling back to the themes it inherits from,
+//! `hicolor` and finally the `pixmaps` directory:
+//!
+//! ```no_run
+//! use azalea_applet::icons;
+//!
+//! icons::set_theme("Adwaita");
+//! let path = icons::lookup("firefox", 24, 1);
+//! ```
+//!
+//! Icon names are never treated as paths; image files are looked up through
+//! [`lookup_file`] instead.
+//!
+//! Hosts render [`Icon`](crate::widgets::icon::Icon) elements through this
+//! module, scaled to the scale factor of their surface.
+//!
+//! [icon theme specification]: https://specifications.freedesktop.org/icon-theme-spec/latest/
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock, RwLock},
+};
+
+/// The theme every other theme falls back to.
+const FALLBACK_THEME: &str = "hicolor";
+
+/// The file types icons are looked up with, in order of preference.
+const EXTENSIONS: [&str; 2] = ["png", "svg"];
+
+#[derive(Debug, Default)]
+struct State {
+    theme: Option<String>,
+    scale: u16,
+    themes: HashMap<String, Option<Arc<Theme>>>,
+    icons: HashMap<(String, u16, u16), Option<PathBuf>>,
+    files: HashMap<PathBuf, bool>,
+}
+
+static STATE: LazyLock<RwLock<State>> = LazyLock::new(Default::default);
+
+/// Sets the icon theme icons are looked up in.
+pub fn set_theme(name: impl Into<String>) {
+    let mut state = STATE.write().unwrap_or_else(|e| e.into_inner());
+    let name = name.into();
+    if state.theme.as_ref() != Some(&name) {
+        state.theme = Some(name);
+        state.icons.clear();
+    }
+}
+
+/// The name of the icon theme icons are looked up in.
+pub fn theme() -> String {
+    STATE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .theme
+        .clone()
+        .unwrap_or_else(|| String::from(FALLBACK_THEME))
+}
+
+/// Sets the scale icons are rendered at by the host.
+pub fn set_scale(scale: u16) {
+    STATE.write().unwrap_or_else(|e| e.into_inner()).scale = scale.max(1);
+}
+
+/// The scale icons are rendered at by the host.
+pub fn scale() -> u16 {
+    STATE.read().unwrap_or_else(|e| e.into_inner()).scale.max(1)
+}
+
+/// Returns the file of the icon with the given name that fits the size and
+/// scale best, if the icon exists.
+///
+/// Names containing a path separator or `..` are never found. Results are
+/// cached until the theme changes.
+pub fn lookup(name: &str, size: u16, scale: u16) -> Option<PathBuf> {
+    if name.is_empty() || name.contains(['/', '\\', '\0']) || name.contains("..") {
+        return None;
+    }
+
+    let key = (name.to_string(), size, scale);
+    if let Some(path) = STATE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .icons
+        .get(&key)
+    {
+        return path.clone();
+    }
+
+    let path = resolve(&theme(), name, size, scale, &load_theme, &pixmap_dirs());
+
+    STATE
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .icons
+        .insert(key, path.clone());
+    path
+}
+
+/// Returns the given image file if it exists.
+///
+/// Only absolute paths to PNG and SVG files are accepted. Results are cached.
+pub fn lookup_file(path: &Path) -> Option<PathBuf> {
+    let image = path
+        .extension()
+        .is_some_and(|ext| EXTENSIONS.iter().any(|image| ext == *image));
+    if !path.is_absolute() || !image {
+        return None;
+    }
+
+    let cached = STATE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .files
+        .get(path)
+        .copied();
+    let exists = cached.unwrap_or_else(|| {
+        let exists = path.is_file();
+        STATE
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .files
+            .insert(path.to_path_buf(), exists);
+        exists
+    });
+
+    exists.then(|| path.to_path_buf())
+}
+
+/// Returns the directories icon themes are installed in, in order of
+/// precedence.
+pub fn search_paths() -> Vec<PathBuf> {
+    let home = env::var_os("HOME").map(PathBuf::from);
+
+    let mut paths = Vec::new();
+    paths.extend(home.as_ref().map(|home| home.join(".icons")));
+    paths.extend(
+        env::var_os("XDG_DATA_HOME")
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| home.as_ref().map(|home| home.join(".local/share")))
+            .map(|path| path.join("icons")),
+    );
+
+    paths.extend(data_dirs().into_iter().map(|dir| dir.join("icons")));
+
+    paths
+}
+
+/// The directories of `XDG_DATA_DIRS`.
+fn data_dirs() -> Vec<PathBuf> {
+    env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|value| !value.is_empty())
+        .unwrap_or_else(|| String::from("/usr/local/share:/usr/share"))
+        .split(':')
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .collect()
+}
+
+fn pixmap_dirs() -> Vec<PathBuf> {
+    data_dirs()
+        .into_iter()
+        .map(|dir| dir.join("pixmaps"))
+        .collect()
+}
+
+/// Looks an icon up in the given theme, the themes it inherits from,
+/// `hicolor` and finally the pixmap directories.
+fn resolve(
+    theme: &str,
+    icon: &str,
+    size: u16,
+    scale: u16,
+    load: &dyn Fn(&str) -> Option<Arc<Theme>>,
+    pixmaps: &[PathBuf],
+) -> Option<PathBuf> {
+    let mut visited = Vec::new();
+    find_in_theme(theme, icon, size, scale, load, &mut visited)
+        .or_else(|| find_in_theme(FALLBACK_THEME, icon, size, scale, load, &mut visited))
+        .or_else(|| {
+            pixmaps
+                .iter()
+                .flat_map(|dir| EXTENSIONS.map(|ext| dir.join(format!("{icon}.{ext}"))))
+                .find(|path| path.is_file())
+        })
+}
+
+fn find_in_theme(
+    name: &str,
+    icon: &str,
+    size: u16,
+    scale: u16,
+    load: &dyn Fn(&str) -> Option<Arc<Theme>>,
+    visited: &mut Vec<String>,
+) -> Option<PathBuf> {
+    if visited.iter().any(|theme| theme == name) {
+        return None;
+    }
+    visited.push(name.to_string());
+
+    let theme = load(name)?;
+    theme.lookup(icon, size, scale).or_else(|| {
+        theme
+            .inherits
+            .iter()
+            .find_map(|parent| find_in_theme(parent, icon, size, scale, load, visited))
+    })
+}
+
+fn load_theme(name: &str) -> Option<Arc<Theme>> {
+    if let Some(theme) = STATE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .themes
+        .get(name)
+    {
+        return theme.clone();
+    }
+
+    let theme = Theme::load(name, &search_paths()).map(Arc::new);
+    STATE
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .themes
+        .insert(name.to_string(), theme.clone());
+    theme
+}
+
+/// An installed icon theme.
+#[derive(Debug)]
+struct Theme {
+    /// The directories of the theme in all search paths.
+    roots: Vec<PathBuf>,
+    inherits: Vec<String>,
+    directories: Vec<Directory>,
+}
+
+impl Theme {
+    /// Loads the theme with the given name from the given search paths.
+    fn load(name: &str, search_paths: &[PathBuf]) -> Option<Self> {
+        let roots: Vec<PathBuf> = search_paths
+            .iter()
+            .map(|path| path.join(name))
+            .filter(|path| path.is_dir())
+            .collect();
+        let index = roots
+            .iter()
+            .find_map(|root| fs::read_to_string(root.join("index.theme")).ok())?;
+        let sections = parse_index(&index);
+
+        let theme = sections.get("Icon Theme")?;
+        let list = |key: &str| -> Vec<String> {
+            theme
+                .get(key)
+                .map(|value| {
+                    value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|value| !value.is_empty())
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        let mut names = list("Directories");
+        names.extend(list("ScaledDirectories"));
+        let directories = names
+            .into_iter()
+            .filter_map(|name| Directory::parse(&name, sections.get(name.as_str())?))
+            .collect();
+
+        Some(Theme {
+            roots,
+            inherits: list("Inherits"),
+            directories,
+        })
+    }
+
+    /// Looks up an icon in this theme only.
+    fn lookup(&self, icon: &str, size: u16, scale: u16) -> Option<PathBuf> {
+        if let Some(path) = self
+            .directories
+            .iter()
+            .filter(|directory| directory.matches(size, scale))
+            .flat_map(|directory| self.files(directory, icon))
+            .find(|path| path.is_file())
+        {
+            return Some(path);
+        }
+
+        self.directories
+            .iter()
+            .flat_map(|directory| {
+                self.files(directory, icon)
+                    .map(move |path| (directory, path))
+            })
+            .filter(|(_, path)| path.is_file())
+            .min_by_key(|(directory, _)| directory.distance(size, scale))
+            .map(|(_, path)| path)
+    }
+
+    /// The files an icon may be stored as in the given directory.
+    fn files<'a>(
+        &'a self,
+        directory: &'a Directory,
+        icon: &'a str,
+    ) -> impl Iterator<Item = PathBuf> + 'a {
+        self.roots.iter().flat_map(move |root| {
+            EXTENSIONS.map(|ext| root.join(&directory.name).join(format!("{icon}.{ext}")))
+        })
+    }
+}
+
+/// A directory of icons of one size in a [`Theme`].
+#[derive(Debug)]
+struct Directory {
+    name: String,
+    size: u16,
+    scale: u16,
+    kind: Kind,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    Fixed,
+    Scalable { min: u16, max: u16 },
+    Threshold(u16),
+}
+
+impl Directory {
+    fn parse(name: &str, section: &HashMap<&str, &str>) -> Option<Self> {
+        let number = |key: &str| section.get(key).and_then(|value| value.parse::<u16>().ok());
+        let size = number("Size")?;
+
+        let kind = match section.get("Type").copied() {
+            Some("Fixed") => Kind::Fixed,
+            Some("Scalable") => Kind::Scalable {
+                min: number("MinSize").unwrap_or(size),
+                max: number("MaxSize").unwrap_or(size),
+            },
+            _ => Kind::Threshold(number("Threshold").unwrap_or(2)),
+        };
+
+        Some(Directory {
+            name: name.to_string(),
+            size,
+            scale: number("Scale").unwrap_or(1),
+            kind,
+        })
+    }
+
+    fn matches(&self, size: u16, scale: u16) -> bool {
+        if self.scale != scale {
+            return false;
+        }
+
+        match self.kind {
+            Kind::Fixed => self.size == size,
+            Kind::Scalable { min, max } => (min..=max).contains(&size),
+            Kind::Threshold(threshold) => {
+                self.size.saturating_sub(threshold) <= size && size <= self.size + threshold
+            }
+        }
+    }
+
+    fn distance(&self, size: u16, scale: u16) -> u32 {
+        let size = u32::from(size) * u32::from(scale);
+        let scaled = |size: u16| u32::from(size) * u32::from(self.scale);
+
+        match self.kind {
+            Kind::Fixed => scaled(self.size).abs_diff(size),
+            Kind::Scalable { min, max } => {
+                if size < scaled(min) {
+                    scaled(min) - size
+                } else {
+                    size.saturating_sub(scaled(max))
+                }
+            }
+            Kind::Threshold(threshold) => {
+                let (min, max) = (
+                    scaled(self.size.saturating_sub(threshold)),
+                    scaled(self.size + threshold),
+                );
+                if size < min {
+                    min - size
+                } else {
+                    size.saturating_sub(max)
+                }
+            }
+        }
+    }
+}
+
+/// Parses the sections of an `index.theme` file.
+fn parse_index(source: &str) -> HashMap<&str, HashMap<&str, &str>> {
+    let mut sections: HashMap<&str, HashMap<&str, &str>> = HashMap::new();
+    let mut current = None;
+
+    for line in source.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            current = Some(name);
+            sections.entry(name).or_default();
+        } else if let Some(section) = current
+            && let Some((key, value)) = line.split_once('=')
+        {
+            sections
+                .entry(section)
+                .or_default()
+                .insert(key.trim(), value.trim());
+        }
+    }
+
+    sections
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::process;
+
+    const INDEX: &str = "
+# A comment
+[Icon Theme]
+Name = Test
+Inherits=parent, ,hicolor
+Directories=16x16/apps,scalable/apps
+
+[16x16/apps]
+Size=16
+Type=Fixed
+
+[scalable/apps]
+Size=48
+MinSize=8
+MaxSize=512
+Type=Scalable
+";
+
+    fn directory(index: &str, name: &str) -> Directory {
+        let sections = parse_index(index);
+        Directory::parse(name, &sections[name]).unwrap()
+    }
+
+    #[test]
+    fn index_is_parsed_into_sections() {
+        let sections = parse_index(INDEX);
+
+        assert_eq!(sections.len(), 3);
+        assert_eq!(sections["Icon Theme"]["Name"], "Test");
+        assert_eq!(sections["Icon Theme"]["Inherits"], "parent, ,hicolor");
+        assert_eq!(sections["scalable/apps"]["MaxSize"], "512");
+        assert!(!sections["Icon Theme"].contains_key("# A comment"));
+    }
+
+    #[test]
+    fn directories_match_their_sizes() {
+        let fixed = directory(INDEX, "16x16/apps");
+        assert!(fixed.matches(16, 1));
+        assert!(!fixed.matches(16, 2));
+        assert!(!fixed.matches(24, 1));
+        assert_eq!(fixed.distance(16, 1), 0);
+        assert_eq!(fixed.distance(24, 1), 8);
+        assert_eq!(fixed.distance(16, 2), 16);
+
+        let scalable = directory(INDEX, "scalable/apps");
+        assert!(scalable.matches(8, 1));
+        assert!(scalable.matches(512, 1));
+        assert!(!scalable.matches(4, 1));
+        assert_eq!(scalable.distance(4, 1), 4);
+        assert_eq!(scalable.distance(600, 1), 88);
+
+        let threshold = directory("[32]\nSize=32\n", "32");
+        assert!(threshold.matches(30, 1));
+        assert!(threshold.matches(34, 1));
+        assert!(!threshold.matches(35, 1));
+        assert_eq!(threshold.distance(40, 1), 6);
+    }
+
+    #[test]
+    fn icons_fall_back_to_parents_hicolor_and_pixmaps() {
+        let root = env::temp_dir().join(format!("azalea-icons-{}", process::id()));
+        let icons = root.join("icons");
+        let pixmaps = root.join("pixmaps");
+        let theme = |name: &str, inherits: &str| {
+            let directory = icons.join(name);
+            fs::create_dir_all(directory.join("16x16/apps")).unwrap();
+            let index = format!(
+                "[Icon Theme]\nInherits={inherits}\nDirectories=16x16/apps\n\
+                 [16x16/apps]\nSize=16\nType=Fixed\n"
+            );
+            fs::write(directory.join("index.theme"), index).unwrap();
+        };
+        let icon = |path: PathBuf| {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, "").unwrap();
+            path
+        };
+
+        theme("test", "parent");
+        theme("parent", "");
+        theme(FALLBACK_THEME, "");
+        let own = icon(icons.join("test/16x16/apps/own.png"));
+        let inherited = icon(icons.join("parent/16x16/apps/inherited.svg"));
+        let fallback = icon(icons.join("hicolor/16x16/apps/fallback.png"));
+        let pixmap = icon(pixmaps.join("pixmap.png"));
+        for name in ["own", "inherited", "fallback"] {
+            icon(pixmaps.join(format!("{name}.png")));
+        }
+        icon(icons.join("hicolor/16x16/apps/own.png"));
+        icon(icons.join("hicolor/16x16/apps/inherited.png"));
+
+        let load = |name: &str| Theme::load(name, std::slice::from_ref(&icons)).map(Arc::new);
+        let resolve =
+            |name: &str| resolve("test", name, 16, 1, &load, std::slice::from_ref(&pixmaps));
+
+        assert_eq!(resolve("own"), Some(own));
+        assert_eq!(resolve("inherited"), Some(inherited));
+        assert_eq!(resolve("fallback"), Some(fallback));
+        assert_eq!(resolve("pixmap"), Some(pixmap));
+        assert_eq!(resolve("missing"), None);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn paths_are_not_icon_names() {
+        for name in ["", "/etc/passwd", "../secret", "apps/firefox", ".."] {
+            assert_eq!(lookup(name, 16, 1), None);
+        }
+
+        assert_eq!(lookup_file(Path::new("Cargo.toml")), None);
+        assert_eq!(lookup_file(Path::new("/etc/passwd")), None);
+    }
+}
@@ -1,40 +1,140 @@
+pub mod config;
 pub mod connection;
+pub mod diff;
+pub mod error;
+pub mod event;
+pub mod icons;
 pub mod runtime;
 pub mod widgets;
 use std::{
+    borrow::Cow,
     cell::RefCell,
+    collections::HashMap,
+    env,
     fmt::Debug,
+    path::PathBuf,
     sync::{
         Arc, OnceLock,
         atomic::{AtomicUsize, Ordering},
     },
+    time::Duration,
 };
 
 pub use bincode;
 pub use iced;
 pub use serde;
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
-use tokio::{net::UnixStream, runtime::Runtime};
+use tokio::{net::UnixStream, runtime::Runtime, task::AbortHandle};
 pub use widgets::element::Element;
 
-use crate::{connection::Connection, runtime::Plugin, widgets::serde_types::Theme};
+use crate::{
+    connection::Connection,
+    diff::Patch,
+    error::Error,
+    event::{Event, Interests, KeyboardEvent, Output, PointerEvent, Surface},
+    runtime::Plugin,
+    widgets::{custom, popup::Popup, registry, serde_types::Theme},
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum PluginEvent {
     Update,
     Message(Vec<u8>),
     Theme(Theme),
+    Keyboard(KeyboardEvent),
+    Pointer(PointerEvent),
+    Resized(Surface),
+    Focus(bool),
+    Visibility(bool),
+    Shutdown,
+    /// Asks the plugin to send its full view, as the host could not apply
+    /// its patches.
+    Resync,
+    /// The custom widgets announced by the plugin that the host supports.
+    Widgets(Vec<String>),
+    /// Checks that the plugin is still responding, which it does by replying
+    /// with [`PluginRequest::Heartbeat`].
+    Heartbeat,
+    /// The host closed the popup with the given name.
+    PopupDismissed(String),
+    /// The outputs the host shows panels on, sent to plugins interested in
+    /// them when they connect and whenever outputs are added or removed.
+    Outputs(Vec<Output>),
+    /// A message produced by the view of the plugin on the named output.
+    OutputMessage(String, Vec<u8>),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum PluginRequest {
     View(Arc<Element>),
+    /// Patches against the view with the given revision, see [`diff`].
+    ///
+    /// A full [`PluginRequest::View`] has revision `0`, every patch
+    /// increments it.
+    Patch(u64, Arc<Vec<Patch>>),
     Message(Vec<u8>),
+    Subscribe(Interests),
+    /// An event could not be handled by the plugin.
+    Error(Error),
+    /// The reply to [`PluginEvent::Heartbeat`].
+    Heartbeat,
+    /// The first request of every plugin.
+    Hello {
+        interests: Interests,
+        /// The kinds of custom widgets the plugin may use.
+        widgets: Vec<String>,
+    },
+    /// A font the host should load, sent right after
+    /// [`PluginRequest::Hello`].
+    ///
+    /// Its faces are referenced by the family name stored in the font, see
+    /// [`Font::with_name`](widgets::serde_types::Font::with_name).
+    Font(Cow<'static, [u8]>),
+    /// Opens a popup, or replaces the open popup with the same name.
+    Popup(Arc<Popup>),
+    /// Closes the popup with the given name.
+    ClosePopup(String),
+    /// The view of the plugin on the named output, see
+    /// [`PluginRequest::View`].
+    OutputView(String, Arc<Element>),
+    /// Patches against the view on the named output, see
+    /// [`PluginRequest::Patch`].
+    OutputPatch(String, u64, Arc<Vec<Patch>>),
+}
+
+impl PluginRequest {
+    /// Whether the request answers an event of the host.
+    ///
+    /// Plugins write exactly one reply per event. All other requests, such
+    /// as fonts, popups and the views of outputs, are sent along with the
+    /// replies.
+    pub fn is_reply(&self) -> bool {
+        matches!(
+            self,
+            PluginRequest::View(_)
+                | PluginRequest::Patch(..)
+                | PluginRequest::Error(_)
+                | PluginRequest::Heartbeat
+        )
+    }
 }
 #[derive(Debug, Clone)]
 pub enum RuntimeMessage {
-    New(Plugin, usize),
+    New(Box<Plugin>, usize),
     Request(PluginRequest, usize),
+    /// A request produced by the view of a plugin on the named output.
+    OutputRequest(PluginRequest, usize, String),
+    Pointer(PointerEvent, usize),
+    Focus(usize),
+    Error(Error, Option<usize>),
+    /// The plugin did not reply in time.
+    Unresponsive(usize),
+    /// The plugin replied again after being unresponsive.
+    Responsive(usize),
+    /// The plugin has been unresponsive for too long and should be restarted.
+    ///
+    /// Its connection is closed right after.
+    Hung(usize),
     Shutdown(usize),
 }
 
@@ -42,61 +142,368 @@ pub trait Application {
     type Message: Sync + Send + Serialize + DeserializeOwned + Debug + Clone;
     fn new() -> Self;
     fn update(&mut self, message: Self::Message);
-    fn view(&self, theme: &Theme) -> Element;
+    fn view(&self, theme: &Theme) -> Element<Self::Message>;
+
+    /// The view of the plugin on the given output.
+    ///
+    /// Only used if the plugin is interested in
+    /// [outputs](Interests::outputs), the host shows [`Application::view`]
+    /// otherwise.
+    fn output_view(&self, _output: &Output, theme: &Theme) -> Element<Self::Message> {
+        self.view(theme)
+    }
+
+    /// Handles a message produced by the view on the named output.
+    fn update_from(&mut self, _output: &str, message: Self::Message) {
+        self.update(message);
+    }
+
+    /// The [popups](widgets::popup) the plugin has open.
+    ///
+    /// Popups are sent to the host when they open or change, and closed when
+    /// they are no longer returned.
+    fn popups(&self, _theme: &Theme) -> Vec<Popup<Self::Message>> {
+        Vec::new()
+    }
+
+    /// The input events the plugin wants the host to forward.
+    fn interests(&self) -> Interests {
+        Interests::default()
+    }
+
+    /// The kinds of [custom widgets](widgets::custom) the plugin may use.
+    ///
+    /// The host announces which of them it supports, others should be
+    /// replaced with fallbacks, see [`custom_or`](widgets::custom::custom_or).
+    fn widgets(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// The fonts the plugin uses that the host may not have installed, such
+    /// as icon fonts.
+    ///
+    /// They are uploaded once per connection, before the first view.
+    fn fonts(&self) -> Vec<Cow<'static, [u8]>> {
+        Vec::new()
+    }
+
+    /// Whether the plugin keeps its state when it reconnects to the host.
+    ///
+    /// Otherwise it starts over from [`Application::new`].
+    fn resume(&self) -> bool {
+        true
+    }
+
+    /// Handles an error that did not stop the plugin, such as a message that
+    /// could not be decoded or a lost connection to the host.
+    ///
+    /// The host is informed about errors affecting single events on its
+    /// own, and the plugin reconnects after losing the connection.
+    fn on_error(&mut self, _error: Error) {}
+
+    /// Handles an input event, optionally producing a message for
+    /// [`Application::update`].
+    fn event(&mut self, _event: Event) -> Option<Self::Message> {
+        None
+    }
 }
 
+/// How long a plugin first waits before connecting to the host again.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(250);
+
+/// The longest a plugin waits before connecting to the host again.
+const MAX_BACKOFF: Duration = Duration::from_secs(10);
+
 pub fn run<A>(mut app: A)
 where
     A: Application,
 {
     tokio_runtime().block_on(async move {
-        let stream = UnixStream::connect("/tmp/sock").await.unwrap();
-
-        let mut connection = Connection::new(stream);
-        let view_theme = RefCell::new(Theme::from(iced::Theme::default()));
+        let mut theme = Theme::default();
+        let mut backoff = INITIAL_BACKOFF;
+        let mut connected = false;
         loop {
-            if let Ok(Some(event)) = connection.read_frame().await {
-                match event {
-                    PluginEvent::Update => {
-
-                        let element = app.view(&view_theme.borrow());
-                        connection
-                            .write_frame(PluginRequest::View(Arc::new(element)))
-                            .await
-                            .unwrap();
-                    }
-                    PluginEvent::Message(items) => {
-                        let message: A::Message =
-                            bincode::serde::decode_from_slice(&items, bincode::config::standard())
-                                .unwrap()
-                                .0;
-                        let _ = app.update(message);
-
-                        let element = app.view(&view_theme.borrow());
-                        connection
-                            .write_frame(PluginRequest::View(Arc::new(element)))
-                            .await
-                            .unwrap();
+            match UnixStream::connect(socket_path()).await {
+                Ok(stream) => {
+                    if connected && !app.resume() {
+                        app = A::new();
                     }
-                    PluginEvent::Theme(theme) => {
-                        *view_theme.borrow_mut() = theme;
-                        let element = app.view(&view_theme.borrow());
-                        connection
-                            .write_frame(PluginRequest::View(Arc::new(element)))
-                            .await
-                            .unwrap();
+                    connected = true;
+                    backoff = INITIAL_BACKOFF;
+
+                    // The host is sent the full view again, as a restarted
+                    // host knows nothing about the plugin.
+                    match serve(&mut app, &mut theme, Connection::new(stream)).await {
+                        Ok(()) => break,
+                        Err(error) => app.on_error(error),
                     }
                 }
+                Err(error) => app.on_error(error.into()),
             }
+
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
         }
     });
 }
 
+/// Answers the events of the host until it asks the plugin to shut down.
+///
+/// Fails once the connection is unusable; errors that only affect a single
+/// event are reported to the host instead.
+async fn serve<A>(app: &mut A, theme: &mut Theme, mut connection: Connection) -> Result<(), Error>
+where
+    A: Application,
+{
+    connection
+        .write_frame(&PluginRequest::Hello {
+            interests: app.interests(),
+            widgets: app.widgets(),
+        })
+        .await?;
+    for font in app.fonts() {
+        connection.write_frame(&PluginRequest::Font(font)).await?;
+    }
+
+    let interests = app.interests();
+    let mut sent = SentView::default();
+    let mut outputs: Vec<Output> = Vec::new();
+    let mut output_views: HashMap<String, SentView> = HashMap::new();
+    // The encoded popups sent to the host, by name.
+    let mut popups: HashMap<String, Vec<u8>> = HashMap::new();
+    loop {
+        let event = match connection.read_frame().await {
+            Ok(Some(event)) => event,
+            Ok(None) => continue,
+            Err(error) if error.is_fatal() => return Err(error),
+            Err(error) => {
+                // The host waits for a reply to every event, even one the
+                // plugin could not read.
+                app.on_error(error.clone());
+                connection.write_frame(&PluginRequest::Error(error)).await?;
+                continue;
+            }
+        };
+
+        let input = match event {
+            PluginEvent::Update => None,
+            PluginEvent::Resync => {
+                sent = SentView::default();
+                output_views.clear();
+                None
+            }
+            PluginEvent::Widgets(kinds) => {
+                registry::set_supported(kinds);
+                None
+            }
+            PluginEvent::Heartbeat => {
+                connection.write_frame(&PluginRequest::Heartbeat).await?;
+                continue;
+            }
+            PluginEvent::Message(items) => {
+                match bincode::serde::decode_from_slice(&items, bincode::config::standard()) {
+                    Ok((message, _)) => app.update(message),
+                    Err(error) => {
+                        let error = Error::from(error);
+                        app.on_error(error.clone());
+                        connection.write_frame(&PluginRequest::Error(error)).await?;
+                        continue;
+                    }
+                }
+                None
+            }
+            PluginEvent::OutputMessage(output, items) => {
+                match bincode::serde::decode_from_slice(&items, bincode::config::standard()) {
+                    Ok((message, _)) => app.update_from(&output, message),
+                    Err(error) => {
+                        let error = Error::from(error);
+                        app.on_error(error.clone());
+                        connection.write_frame(&PluginRequest::Error(error)).await?;
+                        continue;
+                    }
+                }
+                None
+            }
+            PluginEvent::Outputs(new_outputs) => {
+                output_views.retain(|name, _| new_outputs.iter().any(|o| o.name == *name));
+                outputs = new_outputs.clone();
+                Some(Event::Outputs(new_outputs))
+            }
+            PluginEvent::Theme(new_theme) => {
+                *theme = new_theme;
+                None
+            }
+            PluginEvent::Keyboard(event) => Some(Event::Keyboard(event)),
+            PluginEvent::Pointer(event) => Some(Event::Pointer(event)),
+            PluginEvent::Resized(surface) => Some(Event::Resized(surface)),
+            PluginEvent::Focus(focused) => Some(Event::Focus(focused)),
+            PluginEvent::Visibility(visible) => Some(Event::Visibility(visible)),
+            PluginEvent::PopupDismissed(name) => {
+                // Sent again if the plugin keeps it open.
+                popups.remove(&name);
+                Some(Event::PopupDismissed(name))
+            }
+            PluginEvent::Shutdown => return Ok(()),
+        };
+
+        if let Some(message) = input.and_then(|event| app.event(event)) {
+            app.update(message);
+        }
+
+        // Messages are only erased here, so a view can only produce
+        // messages the plugin is able to decode again. Messages that cannot
+        // be encoded are reported once the views are built, and the widgets
+        // producing them get no handler.
+        let failed = RefCell::new(Vec::new());
+        let erase = |message: A::Message| {
+            bincode::serde::encode_to_vec(message, bincode::config::standard())
+                .map_err(|error| failed.borrow_mut().push(Error::from(error)))
+                .ok()
+        };
+        let update = sent.diff(app.view(theme).filter_map(&erase));
+        let request = match &update {
+            Update::Full(element) => PluginRequest::View(element.clone()),
+            Update::Patch(revision, patches) => PluginRequest::Patch(*revision, patches.clone()),
+        };
+        connection.write_frame(&request).await?;
+        drop(request);
+        sent.commit(update);
+
+        // Views of outputs and popups follow the reply, the host handles
+        // them as they arrive.
+        if interests.outputs {
+            for output in &outputs {
+                let sent = output_views.entry(output.name.clone()).or_default();
+                let update = sent.diff(app.output_view(output, theme).filter_map(&erase));
+                let name = output.name.clone();
+                let request = match &update {
+                    Update::Full(element) => PluginRequest::OutputView(name, element.clone()),
+                    Update::Patch(revision, patches) => {
+                        PluginRequest::OutputPatch(name, *revision, patches.clone())
+                    }
+                };
+                connection.write_frame(&request).await?;
+                drop(request);
+                sent.commit(update);
+            }
+        }
+
+        let mut open = Vec::new();
+        for popup in app.popups(theme) {
+            let popup = popup.filter_map(&erase);
+            let encoded = match bincode::serde::encode_to_vec(&popup, bincode::config::standard())
+            {
+                Ok(encoded) => encoded,
+                Err(error) => {
+                    failed.borrow_mut().push(error.into());
+                    continue;
+                }
+            };
+            open.push(popup.name.clone());
+            if popups.get(&popup.name) != Some(&encoded) {
+                popups.insert(popup.name.clone(), encoded);
+                connection
+                    .write_frame(&PluginRequest::Popup(Arc::new(popup)))
+                    .await?;
+            }
+        }
+        let closed: Vec<String> = popups
+            .keys()
+            .filter(|name| !open.contains(name))
+            .cloned()
+            .collect();
+        for name in closed {
+            popups.remove(&name);
+            connection.write_frame(&PluginRequest::ClosePopup(name)).await?;
+        }
+
+        for error in failed.take().into_iter().chain(custom::take_errors()) {
+            app.on_error(error);
+        }
+    }
+}
+
+/// The last view sent to the host, which new views are diffed against.
+#[derive(Default)]
+struct SentView {
+    element: Option<Element>,
+    revision: u64,
+}
+
+/// How a view is sent to the host.
+enum Update {
+    Full(Arc<Element>),
+    Patch(u64, Arc<Vec<Patch>>),
+}
+
+impl SentView {
+    /// Diffs a new view against the sent one, if there is one.
+    fn diff(&mut self, element: Element) -> Update {
+        match self.element.as_mut() {
+            Some(old) => Update::Patch(self.revision, Arc::new(diff::diff(old, element))),
+            None => Update::Full(Arc::new(element)),
+        }
+    }
+
+    /// Records that an update was sent to the host.
+    ///
+    /// The update must not be shared anymore.
+    fn commit(&mut self, update: Update) {
+        match update {
+            Update::Patch(_, patches) => {
+                self.revision += 1;
+                let applied = self.element.as_mut().zip(Arc::into_inner(patches));
+                if let Some((old, patches)) = applied
+                    && diff::apply(old, patches).is_err()
+                {
+                    self.element = None;
+                }
+            }
+            Update::Full(element) => {
+                self.revision = 0;
+                self.element = Arc::into_inner(element);
+            }
+        }
+    }
+}
+
+/// The environment variable holding the path of the host socket.
+pub const SOCKET_ENV: &str = "AZALEA_SOCKET";
+
+/// Returns the path of the socket plugins connect to.
+///
+/// This is taken from [`SOCKET_ENV`] when set, which is how supervised
+/// plugins learn where their host listens.
+pub fn socket_path() -> PathBuf {
+    env::var_os(SOCKET_ENV)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("/tmp/sock"))
+}
+
 pub(crate) fn tokio_runtime() -> &'static Runtime {
     static RUNTIME: OnceLock<Runtime> = OnceLock::new();
     RUNTIME.get_or_init(|| Runtime::new().expect("Setting up tokio runtime needs to succeed."))
 }
 
+/// Runs a future on the [`tokio_runtime`] until it completes, or until the
+/// returned future is dropped.
+///
+/// Subscriptions are polled by the executor of iced, so the streams behind
+/// them use this to do their I/O on the runtime and stop with them.
+pub(crate) async fn on_tokio_runtime(future: impl Future<Output = ()> + Send + 'static) {
+    struct Abort(AbortHandle);
+
+    impl Drop for Abort {
+        fn drop(&mut self) {
+            self.0.abort();
+        }
+    }
+
+    let task = tokio_runtime().spawn(future);
+    let _abort = Abort(task.abort_handle());
+    let _ = task.await;
+}
+
 static COUNTER: AtomicUsize = AtomicUsize::new(0);
 fn unique_id() -> usize {
     COUNTER.fetch_add(1, Ordering::Relaxed)
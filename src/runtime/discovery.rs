@@ -0,0 +1,378 @@
+//! Finding installed plugins through manifest files.
+//!
+//! Every plugin ships a `<name>.toml` manifest in one of the `azalea/plugins`
+//! directories below the XDG config and data directories:
+//!
+//! ```toml
+//! name = "clock"
+//! executable = "azalea-clock"
+//! version = "0.1.0"
+//! icon = "preferences-system-time"
+//! permissions = ["notifications"]
+//!
+//! [placement]
+//! slot = "end"
+//! priority = 10
+//! ```
+//!
+//! A relative `executable` is resolved against the directory of the
+//! manifest. Manifests found earlier in the search order shadow later ones
+//! with the same name, so users can override system-wide plugins.
+use std::{
+    env,
+    error::Error,
+    ffi::OsString,
+    fmt, fs, io,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::runtime::{layout::Placement, supervisor::Launch};
+
+/// The manifest of an installed plugin.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// The unique name of the plugin.
+    pub name: String,
+    /// The executable of the plugin.
+    pub executable: PathBuf,
+    /// The version of the plugin.
+    pub version: String,
+    /// An icon name or path representing the plugin.
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// The permissions the plugin requests from the host.
+    #[serde(default)]
+    pub permissions: Vec<String>,
+    /// Where the plugin is placed unless configured otherwise.
+    #[serde(default)]
+    pub placement: Placement,
+    /// The manifest file the plugin was discovered from.
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+impl Manifest {
+    /// Reads and validates the manifest at the given path.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ManifestError> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path).map_err(|e| ManifestError::Io(path.into(), e))?;
+        let mut manifest: Manifest =
+            toml::from_str(&source).map_err(|e| ManifestError::Parse(path.into(), e))?;
+
+        manifest.path = path.into();
+        if manifest.executable.is_relative()
+            && let Some(directory) = path.parent()
+        {
+            manifest.executable = directory.join(&manifest.executable);
+        }
+        manifest.validate()?;
+
+        Ok(manifest)
+    }
+
+    /// Returns how the plugin executable is launched.
+    pub fn launch(&self) -> Launch {
+        Launch::new(&self.name, &self.executable)
+    }
+
+    fn validate(&self) -> Result<(), ManifestError> {
+        let invalid = |reason: &str| Err(ManifestError::Invalid(self.path.clone(), reason.into()));
+
+        if self.name.is_empty()
+            || !self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        {
+            return invalid(
+                "`name` must be non-empty and only contain ASCII letters, digits, `-`, `_` and `.`",
+            );
+        }
+        if self.version.trim().is_empty() {
+            return invalid("`version` must not be empty");
+        }
+        if self.placement.slot.is_empty() {
+            return invalid("`placement.slot` must not be empty");
+        }
+
+        match fs::metadata(&self.executable) {
+            Ok(metadata) if metadata.is_file() && metadata.permissions().mode() & 0o111 != 0 => {
+                Ok(())
+            }
+            Ok(_) => invalid("`executable` is not an executable file"),
+            Err(_) => invalid("`executable` does not exist"),
+        }
+    }
+}
+
+/// An error produced while loading a [`Manifest`].
+#[derive(Debug)]
+pub enum ManifestError {
+    /// The manifest could not be read.
+    Io(PathBuf, io::Error),
+    /// The manifest is not valid TOML or misses fields.
+    Parse(PathBuf, toml::de::Error),
+    /// The manifest has invalid values.
+    Invalid(PathBuf, String),
+}
+
+impl ManifestError {
+    /// The manifest file the error occurred in.
+    pub fn path(&self) -> &Path {
+        match self {
+            ManifestError::Io(path, _)
+            | ManifestError::Parse(path, _)
+            | ManifestError::Invalid(path, _) => path,
+        }
+    }
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ManifestError::Io(path, error) => write!(f, "{}: {error}", path.display()),
+            ManifestError::Parse(path, error) => write!(f, "{}: {error}", path.display()),
+            ManifestError::Invalid(path, reason) => write!(f, "{}: {reason}", path.display()),
+        }
+    }
+}
+
+impl Error for ManifestError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ManifestError::Io(_, error) => Some(error),
+            ManifestError::Parse(_, error) => Some(error),
+            ManifestError::Invalid(..) => None,
+        }
+    }
+}
+
+/// The result of scanning the manifest directories.
+#[derive(Debug, Default)]
+pub struct Discovery {
+    /// The valid manifests, in search order.
+    pub manifests: Vec<Manifest>,
+    /// The manifests that failed to load.
+    pub errors: Vec<ManifestError>,
+}
+
+/// Returns the directories searched for manifests, in order of precedence.
+pub fn search_paths() -> Vec<PathBuf> {
+    search_paths_with(|var| env::var_os(var))
+}
+
+/// Returns the directories searched for manifests, reading the environment
+/// through `var`.
+fn search_paths_with(var: impl Fn(&str) -> Option<OsString>) -> Vec<PathBuf> {
+    let var = |name: &str| var(name).filter(|value| !value.is_empty());
+    let home = var("HOME").map(PathBuf::from);
+    let xdg = |name: &str, fallback: &str| {
+        var(name)
+            .map(PathBuf::from)
+            .or_else(|| home.as_ref().map(|home| home.join(fallback)))
+    };
+
+    let mut paths = Vec::new();
+    paths.extend(xdg("XDG_CONFIG_HOME", ".config"));
+    paths.extend(xdg("XDG_DATA_HOME", ".local/share"));
+
+    let data_dirs = var("XDG_DATA_DIRS")
+        .and_then(|value| value.into_string().ok())
+        .unwrap_or_else(|| String::from("/usr/local/share:/usr/share"));
+    paths.extend(
+        data_dirs
+            .split(':')
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from),
+    );
+
+    paths
+        .into_iter()
+        .map(|path| path.join("azalea").join("plugins"))
+        .collect()
+}
+
+/// Scans the [`search_paths`] for plugin manifests.
+pub fn discover() -> Discovery {
+    discover_in(search_paths())
+}
+
+/// Scans the given directories for plugin manifests.
+pub fn discover_in(paths: impl IntoIterator<Item = impl AsRef<Path>>) -> Discovery {
+    let mut discovery = Discovery::default();
+
+    for directory in paths {
+        let Ok(entries) = fs::read_dir(directory.as_ref()) else {
+            continue;
+        };
+        let mut files: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+            .collect();
+        files.sort();
+
+        for file in files {
+            match Manifest::load(&file) {
+                Ok(manifest) => {
+                    if !discovery.manifests.iter().any(|m| m.name == manifest.name) {
+                        discovery.manifests.push(manifest);
+                    }
+                }
+                Err(error) => discovery.errors.push(error),
+            }
+        }
+    }
+
+    discovery
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+
+    use super::*;
+
+    /// Creates an empty directory for the named test.
+    fn root(test: &str) -> PathBuf {
+        let root = env::temp_dir().join(format!("azalea-discovery-{test}-{}", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    fn write(path: PathBuf, contents: &str) -> PathBuf {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn executable(path: PathBuf) -> PathBuf {
+        let path = write(path, "#!/bin/sh\n");
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    fn manifest(name: &str, version: &str) -> String {
+        format!("name = \"{name}\"\nexecutable = \"{name}\"\nversion = \"{version}\"\n")
+    }
+
+    #[test]
+    fn search_paths_follow_xdg_precedence() {
+        let var = |name: &str| {
+            let value = match name {
+                "HOME" => "/home/user",
+                "XDG_CONFIG_HOME" => "/config",
+                "XDG_DATA_HOME" => "",
+                "XDG_DATA_DIRS" => "/opt/share::/usr/share",
+                _ => return None,
+            };
+            Some(OsString::from(value))
+        };
+
+        assert_eq!(
+            search_paths_with(var),
+            [
+                "/config/azalea/plugins",
+                "/home/user/.local/share/azalea/plugins",
+                "/opt/share/azalea/plugins",
+                "/usr/share/azalea/plugins",
+            ]
+            .map(PathBuf::from)
+        );
+
+        let unset = search_paths_with(|name| (name == "HOME").then(|| "/home/user".into()));
+        assert_eq!(unset[0], Path::new("/home/user/.config/azalea/plugins"));
+        assert_eq!(unset[3], Path::new("/usr/share/azalea/plugins"));
+    }
+
+    #[test]
+    fn user_manifests_shadow_system_ones() {
+        let root = root("shadow");
+        let user = root.join("config/azalea/plugins");
+        let system = root.join("share/azalea/plugins");
+        write(user.join("clock.toml"), &manifest("clock", "2.0.0"));
+        let clock = executable(user.join("clock"));
+        write(system.join("clock.toml"), &manifest("clock", "1.0.0"));
+        executable(system.join("clock"));
+        write(system.join("weather.toml"), &manifest("weather", "1.0.0"));
+        let weather = executable(system.join("weather"));
+
+        let discovery = discover_in([&user, &system]);
+
+        assert!(discovery.errors.is_empty(), "{:?}", discovery.errors);
+        let found: Vec<(&str, &str, &Path)> = discovery
+            .manifests
+            .iter()
+            .map(|m| (m.name.as_str(), m.version.as_str(), m.executable.as_path()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("clock", "2.0.0", clock.as_path()),
+                ("weather", "1.0.0", weather.as_path()),
+            ]
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn invalid_manifests_are_reported() {
+        let root = root("invalid");
+        let user = root.join("config/azalea/plugins");
+        let system = root.join("share/azalea/plugins");
+        executable(user.join("clock"));
+        write(user.join("not-a-manifest.txt"), "");
+
+        let parse = [
+            write(user.join("broken.toml"), "name = \"broken\"\nversion ="),
+            write(
+                user.join("unversioned.toml"),
+                "name = \"clock\"\nexecutable = \"clock\"\n",
+            ),
+        ];
+        let invalid = [
+            write(user.join("name.toml"), &manifest("../clock", "1.0.0")),
+            write(user.join("version.toml"), &manifest("clock", " ")),
+            write(user.join("missing.toml"), &manifest("missing", "1.0.0")),
+            write(user.join("plain.toml"), &manifest("plain", "1.0.0")),
+            write(
+                user.join("slot.toml"),
+                &format!("{}[placement]\nslot = \"\"\n", manifest("clock", "1.0.0")),
+            ),
+        ];
+        write(user.join("plain"), "");
+        // An invalid user manifest does not shadow a valid system one.
+        write(system.join("clock.toml"), &manifest("clock", "1.0.0"));
+        executable(system.join("clock"));
+
+        let discovery = discover_in([&user, &system]);
+
+        let names: Vec<&str> = discovery
+            .manifests
+            .iter()
+            .map(|m| m.name.as_str())
+            .collect();
+        assert_eq!(names, ["clock"]);
+        assert_eq!(discovery.manifests[0].path, system.join("clock.toml"));
+
+        let mut errors: Vec<(&Path, bool)> = discovery
+            .errors
+            .iter()
+            .map(|e| (e.path(), matches!(e, ManifestError::Parse(..))))
+            .collect();
+        errors.sort();
+        let mut expected: Vec<(&Path, bool)> = parse
+            .iter()
+            .map(|path| (path.as_path(), true))
+            .chain(invalid.iter().map(|path| (path.as_path(), false)))
+            .collect();
+        expected.sort();
+        assert_eq!(errors, expected);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,155 @@
+//! Named slots plugins are placed in.
+//!
+//! A [`Layout`] is an ordered list of slot names. Every plugin has a
+//! [`Placement`] naming its slot and its priority within that slot, so the
+//! order of plugins does not depend on the order they connected in.
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// The slot at the start of the panel.
+pub const START: &str = "start";
+/// The slot in the center of the panel.
+pub const CENTER: &str = "center";
+/// The slot at the end of the panel.
+pub const END: &str = "end";
+
+/// Where a plugin is placed on the panel.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Placement {
+    /// The name of the slot the plugin is placed in.
+    pub slot: String,
+    /// The position within the slot; lower priorities come first.
+    #[serde(default)]
+    pub priority: i32,
+}
+
+impl Placement {
+    /// Creates a [`Placement`] in the given slot.
+    pub fn new(slot: impl Into<String>, priority: i32) -> Self {
+        Self {
+            slot: slot.into(),
+            priority,
+        }
+    }
+}
+
+impl Default for Placement {
+    fn default() -> Self {
+        Self::new(END, 0)
+    }
+}
+
+/// The slots of a panel and the placements configured for named plugins.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    slots: Vec<String>,
+    placements: HashMap<String, Placement>,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self::new([START, CENTER, END])
+    }
+}
+
+impl Layout {
+    /// Creates a [`Layout`] with the given slots, in order.
+    ///
+    /// Slots named more than once are only added the first time.
+    pub fn new(slots: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        let mut layout = Self {
+            slots: Vec::new(),
+            placements: HashMap::new(),
+        };
+        layout.set_slots(slots);
+        layout
+    }
+
+    /// Returns the names of the slots, in order.
+    pub fn slots(&self) -> &[String] {
+        &self.slots
+    }
+
+    /// Appends a slot, unless one with the same name exists.
+    pub fn add_slot(&mut self, slot: impl Into<String>) {
+        let slot = slot.into();
+        if !self.slots.contains(&slot) {
+            self.slots.push(slot);
+        }
+    }
+
+    /// Removes a slot.
+    ///
+    /// Plugins placed in it are no longer shown until they are moved.
+    pub fn remove_slot(&mut self, slot: &str) {
+        self.slots.retain(|s| s != slot);
+    }
+
+    /// Returns the position of a slot, if it exists.
+    pub fn position(&self, slot: &str) -> Option<usize> {
+        self.slots.iter().position(|s| s == slot)
+    }
+
+    /// Returns the placement configured for the named plugin.
+    pub fn placement(&self, name: &str) -> Option<&Placement> {
+        self.placements.get(name)
+    }
+
+    /// Configures the placement of the named plugin.
+    ///
+    /// This takes precedence over the placement in its manifest and is kept
+    /// across restarts of the plugin.
+    pub fn set_placement(&mut self, name: impl Into<String>, placement: Placement) {
+        self.placements.insert(name.into(), placement);
+    }
+
+    /// Forgets the placement configured for the named plugin, so the
+    /// placement in its manifest applies again.
+    pub fn remove_placement(&mut self, name: &str) {
+        self.placements.remove(name);
+    }
+
+    /// Replaces the slots, keeping the placements of all plugins.
+    pub fn set_slots(&mut self, slots: impl IntoIterator<Item = impl Into<String>>) {
+        self.slots.clear();
+        for slot in slots {
+            self.add_slot(slot);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slots_keep_their_order_and_are_unique() {
+        let mut layout = Layout::new(["start", "end", "start"]);
+        assert_eq!(layout.slots(), ["start", "end"]);
+
+        layout.add_slot("tray");
+        layout.add_slot("end");
+        assert_eq!(layout.slots(), ["start", "end", "tray"]);
+        assert_eq!(layout.position("tray"), Some(2));
+        assert_eq!(layout.position("nowhere"), None);
+
+        layout.remove_slot("end");
+        assert_eq!(layout.position("tray"), Some(1));
+    }
+
+    #[test]
+    fn placements_are_replaced_and_outlive_their_slots() {
+        let mut layout = Layout::default();
+        layout.set_placement("clock", Placement::new(START, 1));
+        layout.set_placement("clock", Placement::new(END, 2));
+        assert_eq!(layout.placement("clock"), Some(&Placement::new(END, 2)));
+
+        layout.set_slots(["tray"]);
+        assert_eq!(layout.slots(), ["tray"]);
+        assert_eq!(layout.placement("clock"), Some(&Placement::new(END, 2)));
+
+        layout.remove_placement("clock");
+        assert_eq!(layout.placement("clock"), None);
+    }
+}
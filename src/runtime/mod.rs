@@ -0,0 +1,1075 @@
+pub mod discovery;
+pub mod layout;
+pub mod outputs;
+pub mod supervisor;
+pub mod watch;
+
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    hash::{DefaultHasher, Hash, Hasher},
+    mem,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use iced::widget::mouse_area;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    Element, PluginEvent, PluginRequest, RuntimeMessage,
+    config::{Config, ConfigError},
+    diff,
+    error::Error,
+    icons,
+    event::{Interests, KeyboardEvent, Output, PointerEvent, Surface},
+    runtime::{
+        discovery::{Manifest, ManifestError},
+        layout::{Layout, Placement},
+        supervisor::{Launch, ProcessHandle, Supervisor},
+    },
+    widgets::{
+        element::{to_element, validate},
+        placeholder::unresponsive,
+        popup::Popup,
+        registry,
+        serde_types::Theme,
+    },
+};
+
+#[derive(Debug, Default, Clone)]
+pub struct PluginRuntime {
+    pub plugins: Vec<Plugin>,
+    pub supervisor: Supervisor,
+    pub layout: Layout,
+    manifests: Vec<Manifest>,
+    /// How the enabled plugins were launched, by name.
+    launches: HashMap<String, Launch>,
+    /// The plugins the last applied [`Config`] launched.
+    configured: HashSet<String>,
+    /// The placements the last applied [`Config`] set, by plugin name.
+    placed: HashMap<String, Placement>,
+    focused: Option<usize>,
+    surface: Surface,
+    host_theme: iced::Theme,
+    theme: Theme,
+    /// Hashes of the fonts uploaded by plugins so far.
+    fonts: HashSet<u64>,
+    outputs: Vec<Output>,
+    /// The last error of the runtime itself, not caused by a plugin.
+    error: Option<Error>,
+}
+
+impl PluginRuntime {
+    pub fn new() -> Self {
+        Self {
+            plugins: Vec::new(),
+            supervisor: Supervisor::default(),
+            layout: Layout::default(),
+            manifests: Vec::new(),
+            launches: HashMap::new(),
+            configured: HashSet::new(),
+            placed: HashMap::new(),
+            focused: None,
+            surface: Surface::default(),
+            host_theme: iced::Theme::default(),
+            theme: Theme::default(),
+            fonts: HashSet::new(),
+            outputs: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Handles a message of the plugin connections.
+    ///
+    /// `theme` is the current theme of the host, which is sent to plugins
+    /// when it changed, see [`PluginRuntime::set_theme`].
+    pub fn handle_plugin_message(&mut self, message: RuntimeMessage, theme: iced::Theme) {
+        if self.host_theme != theme {
+            self.host_theme = theme.clone();
+            self.set_theme(theme);
+        }
+
+        match message {
+            RuntimeMessage::Request(message, id) => match message {
+                PluginRequest::Message(items) => {
+                    self.focus(Some(id));
+                    if let Some(plugin) = self.plugins.iter_mut().find(|p| p.id == id)
+                        && plugin.sender.send(PluginEvent::Message(items)).is_err()
+                    {
+                        plugin.error = Some(Error::Disconnected);
+                    }
+                }
+                PluginRequest::View(element) => {
+                    if let Some(plugin) = self.plugins.iter_mut().find(|p| p.id == id) {
+                        // Broken widgets render as placeholders, the rest of
+                        // the view is still shown.
+                        plugin.error = validate(&element).into_iter().next();
+                        plugin.view = Some(element);
+                        plugin.revision = 0;
+                        plugin.resyncing = false;
+                    }
+                }
+                PluginRequest::Patch(base, patches) => {
+                    if let Some(plugin) = self.plugins.iter_mut().find(|p| p.id == id)
+                        && !plugin.resyncing
+                    {
+                        if let Some(view) = plugin.view.as_mut()
+                            && apply(view, &mut plugin.revision, base, patches)
+                        {
+                            plugin.error = validate(view).into_iter().next();
+                        } else {
+                            plugin.resync();
+                        }
+                    }
+                }
+                PluginRequest::OutputView(output, element) => {
+                    if let Some(plugin) = self.plugins.iter_mut().find(|p| p.id == id)
+                        && self.outputs.iter().any(|o| o.name == output)
+                    {
+                        plugin.error = validate(&element).into_iter().next();
+                        plugin.output_views.insert(
+                            output,
+                            OutputView {
+                                view: element,
+                                revision: 0,
+                            },
+                        );
+                    }
+                }
+                PluginRequest::OutputPatch(output, base, patches) => {
+                    if let Some(plugin) = self.plugins.iter_mut().find(|p| p.id == id)
+                        && !plugin.resyncing
+                    {
+                        if let Some(OutputView { view, revision }) =
+                            plugin.output_views.get_mut(&output)
+                            && apply(view, revision, base, patches)
+                        {
+                            plugin.error = validate(view).into_iter().next();
+                        } else {
+                            plugin.resync();
+                        }
+                    }
+                }
+                PluginRequest::Heartbeat => {}
+                PluginRequest::Font(bytes) => self.load_font(bytes),
+                PluginRequest::Popup(popup) => {
+                    if let Some(plugin) = self.plugins.iter_mut().find(|p| p.id == id) {
+                        match validate(&popup.view).into_iter().next() {
+                            Some(error) => plugin.popup_errors.insert(popup.name.clone(), error),
+                            None => plugin.popup_errors.remove(&popup.name),
+                        };
+                        plugin.popups.retain(|p| p.name != popup.name);
+                        plugin.popups.push(popup);
+                    }
+                }
+                PluginRequest::ClosePopup(name) => {
+                    if let Some(plugin) = self.plugins.iter_mut().find(|p| p.id == id) {
+                        plugin.close_popup(&name);
+                    }
+                }
+                PluginRequest::Error(error) => {
+                    if let Some(plugin) = self.plugins.iter_mut().find(|p| p.id == id) {
+                        plugin.error = Some(error);
+                    }
+                }
+                PluginRequest::Subscribe(interests) | PluginRequest::Hello { interests, .. } => {
+                    if let Some(plugin) = self.plugins.iter_mut().find(|p| p.id == id) {
+                        plugin.interests = interests;
+                    }
+                }
+            },
+            RuntimeMessage::OutputRequest(PluginRequest::Message(items), id, output) => {
+                self.focus(Some(id));
+                if let Some(plugin) = self.plugins.iter_mut().find(|p| p.id == id)
+                    && plugin
+                        .sender
+                        .send(PluginEvent::OutputMessage(output, items))
+                        .is_err()
+                {
+                    plugin.error = Some(Error::Disconnected);
+                }
+            }
+            // Views only produce messages.
+            RuntimeMessage::OutputRequest(..) => {}
+            RuntimeMessage::Pointer(event, id) => {
+                if let Some(plugin) = self.plugins.iter().find(|p| p.id == id)
+                    && plugin.interests.pointer
+                {
+                    plugin.notify(PluginEvent::Pointer(event));
+                }
+            }
+            RuntimeMessage::Focus(id) => self.focus(Some(id)),
+            RuntimeMessage::Error(error, Some(id)) => {
+                if let Some(plugin) = self.plugins.iter_mut().find(|p| p.id == id) {
+                    plugin.error = Some(error);
+                }
+            }
+            RuntimeMessage::Error(error, None) => self.error = Some(error),
+            RuntimeMessage::Unresponsive(id) | RuntimeMessage::Responsive(id) => {
+                if let Some(plugin) = self.plugins.iter_mut().find(|p| p.id == id) {
+                    plugin.responding = matches!(message, RuntimeMessage::Responsive(_));
+                }
+            }
+            RuntimeMessage::Hung(id) => {
+                if let Some(plugin) = self.plugins.iter().find(|p| p.id == id) {
+                    plugin.restart();
+                }
+            }
+            RuntimeMessage::New(mut plugin, id) => {
+                plugin.process = plugin
+                    .pid
+                    .and_then(|pid| self.supervisor.attach(pid, plugin.sender.clone()));
+                // Popups of an earlier connection of the same plugin belong
+                // to a process that is gone.
+                for earlier in &mut self.plugins {
+                    if plugin.name().is_some() && earlier.name() == plugin.name() {
+                        earlier.popups.clear();
+                        earlier.popup_errors.clear();
+                    }
+                }
+                // The theme goes first, so the first view of the plugin
+                // already uses it.
+                if plugin
+                    .sender
+                    .send(PluginEvent::Theme(self.theme.clone()))
+                    .is_err()
+                {
+                    plugin.error = Some(Error::Disconnected);
+                }
+                let (supported, unsupported) = registry::negotiate(&plugin.widgets);
+                if let Some(kind) = unsupported.into_iter().next() {
+                    plugin.error = Some(Error::UnknownWidget(kind));
+                }
+                plugin.notify(PluginEvent::Widgets(supported));
+                if plugin.interests.surface {
+                    plugin.notify(PluginEvent::Resized(self.surface));
+                }
+                if plugin.interests.outputs && !self.outputs.is_empty() {
+                    plugin.notify(PluginEvent::Outputs(self.outputs.clone()));
+                }
+                plugin.placement = self.placement_of(&plugin);
+                self.plugins.retain(|p| p.id != id);
+                self.plugins.push(*plugin);
+            }
+            RuntimeMessage::Shutdown(id) => {
+                if self.focused == Some(id) {
+                    self.focused = None;
+                }
+                if let Some(plugin) = self.plugins.iter_mut().find(|p| p.id == id) {
+                    plugin.popups.clear();
+                    plugin.popup_errors.clear();
+                }
+                self.plugins.retain(|p| p.id != id);
+            }
+        }
+    }
+
+    /// Loads a font uploaded by a plugin into the font system of iced.
+    ///
+    /// Plugins upload their fonts every time they connect, so fonts that were
+    /// loaded before are skipped.
+    fn load_font(&mut self, bytes: Cow<'static, [u8]>) {
+        let mut hasher = DefaultHasher::new();
+        bytes.hash(&mut hasher);
+        if self.fonts.insert(hasher.finish()) {
+            iced::advanced::graphics::text::font_system()
+                .write()
+                .unwrap_or_else(|e| e.into_inner())
+                .load_font(bytes);
+        }
+    }
+
+    /// Returns the last error of the runtime that was not caused by a
+    /// plugin, such as failing to listen on the socket.
+    ///
+    /// No plugin can connect after such an error, so hosts should show it.
+    pub fn last_error(&self) -> Option<&Error> {
+        self.error.as_ref()
+    }
+
+    /// The theme plugins are told to render with.
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    /// Sends a new theme to all plugins.
+    ///
+    /// Hosts call this whenever their theme changes, e.g. when switching
+    /// between light and dark mode, or to customize the colors plugins get
+    /// beyond those of an iced theme. Setting the current theme again does
+    /// nothing.
+    pub fn set_theme(&mut self, theme: impl Into<Theme>) {
+        let theme = theme.into();
+        if self.theme == theme {
+            return;
+        }
+
+        let event = PluginEvent::Theme(theme.clone());
+        self.theme = theme;
+        for plugin in &self.plugins {
+            plugin.notify(event.clone());
+        }
+    }
+
+    /// Moves the plugin with the given id to another slot or priority.
+    ///
+    /// Supervised plugins keep their placement when they are restarted.
+    pub fn move_plugin(&mut self, id: usize, placement: Placement) {
+        if let Some(plugin) = self.plugins.iter_mut().find(|p| p.id == id) {
+            if let Some(name) = plugin.name() {
+                self.layout.set_placement(name, placement.clone());
+            }
+            plugin.placement = placement;
+        }
+    }
+
+    /// Returns the plugins placed in the given slot, in order.
+    pub fn plugins_in(&self, slot: &str) -> Vec<&Plugin> {
+        let mut plugins: Vec<&Plugin> = self
+            .plugins
+            .iter()
+            .filter(|p| p.placement.slot == slot)
+            .collect();
+        plugins.sort_by_key(|p| (p.placement.priority, p.id));
+        plugins
+    }
+
+    fn placement_of(&self, plugin: &Plugin) -> Placement {
+        let Some(name) = plugin.name() else {
+            return Placement::default();
+        };
+
+        self.layout
+            .placement(name)
+            .or_else(|| {
+                self.manifests
+                    .iter()
+                    .find(|m| m.name == name)
+                    .map(|m| &m.placement)
+            })
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Launches a plugin executable under the [`Supervisor`].
+    pub fn spawn(&self, launch: Launch) {
+        self.supervisor.spawn(launch);
+    }
+
+    /// Scans the manifest directories for installed plugins.
+    ///
+    /// Manifests that fail to load are skipped and returned.
+    pub fn discover(&mut self) -> Vec<ManifestError> {
+        let discovery = discovery::discover();
+        self.manifests = discovery.manifests;
+        discovery.errors
+    }
+
+    /// Returns the manifests of all installed plugins.
+    pub fn available(&self) -> &[Manifest] {
+        &self.manifests
+    }
+
+    /// Returns whether the installed plugin with the given name is enabled.
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.launches.contains_key(name)
+    }
+
+    /// Launches the installed plugin with the given name.
+    ///
+    /// Returns `false` if no such plugin was discovered.
+    pub fn enable(&mut self, name: &str) -> bool {
+        let Some(manifest) = self.manifests.iter().find(|m| m.name == name) else {
+            return false;
+        };
+
+        let launch = manifest.launch();
+        self.supervisor.spawn(launch.clone());
+        self.launches.insert(manifest.name.clone(), launch);
+        true
+    }
+
+    /// Applies a host [`Config`].
+    ///
+    /// This sets up the slots of all panels, the configured placements and
+    /// the theme, and launches the configured plugins, or every installed
+    /// plugin if none are configured. Hosts should pass
+    /// [`ThemeConfig::to_iced`] as their theme afterwards, so the overrides
+    /// are kept.
+    ///
+    /// Applying a changed config again only touches what changed: plugins
+    /// the config no longer launches are shut down, plugins running
+    /// differently than configured are restarted and placements that changed
+    /// are applied. Plugins moved with [`PluginRuntime::move_plugin`] keep
+    /// their place otherwise, and all other plugins keep running.
+    ///
+    /// Returns errors for plugins that are neither installed nor name an
+    /// executable.
+    ///
+    /// [`ThemeConfig::to_iced`]: crate::config::ThemeConfig::to_iced
+    pub fn configure(&mut self, config: &Config) -> Vec<ConfigError> {
+        self.layout.set_slots(config.layout().slots().iter().cloned());
+        self.host_theme = config.theme.to_iced();
+        self.set_theme(config.theme.to_theme());
+        if let Some(theme) = &config.theme.icon_theme {
+            icons::set_theme(theme);
+        }
+
+        let placed: HashMap<String, Placement> = config
+            .plugins
+            .iter()
+            .filter_map(|plugin| Some((plugin.name.clone(), plugin.placement.clone()?)))
+            .collect();
+        for (name, placement) in &placed {
+            if self.placed.get(name) != Some(placement) {
+                self.layout.set_placement(name, placement.clone());
+            }
+        }
+        for (name, placement) in &self.placed {
+            // Plugins moved since keep their place.
+            if !placed.contains_key(name) && self.layout.placement(name) == Some(placement) {
+                self.layout.remove_placement(name);
+            }
+        }
+        self.placed = placed;
+
+        let mut errors = Vec::new();
+        let launches = self.desired(config, &mut errors);
+        for launch in &launches {
+            match self.launches.get(&launch.name) {
+                Some(running) if running == launch => {}
+                Some(_) => self.supervisor.relaunch(launch.clone()),
+                None => self.supervisor.spawn(launch.clone()),
+            }
+            self.launches.insert(launch.name.clone(), launch.clone());
+        }
+
+        let configured: HashSet<String> = launches.into_iter().map(|l| l.name).collect();
+        let removed: Vec<String> = self.configured.difference(&configured).cloned().collect();
+        for name in removed {
+            self.disable(&name);
+        }
+        self.configured = configured;
+
+        // Plugins the host did not launch have no placement to look up and
+        // stay where they were moved.
+        let placements: Vec<Option<Placement>> = self
+            .plugins
+            .iter()
+            .map(|p| p.name().map(|_| self.placement_of(p)))
+            .collect();
+        for (plugin, placement) in self.plugins.iter_mut().zip(placements) {
+            if let Some(placement) = placement {
+                plugin.placement = placement;
+            }
+        }
+
+        errors
+    }
+
+    /// Returns how the plugins a config wants running are launched: the
+    /// configured plugins, or every installed plugin if none are configured.
+    fn desired(&self, config: &Config, errors: &mut Vec<ConfigError>) -> Vec<Launch> {
+        if config.plugins.is_empty() {
+            return self.manifests.iter().map(Manifest::launch).collect();
+        }
+
+        config
+            .plugins
+            .iter()
+            .filter_map(|plugin| {
+                let manifest = self.manifests.iter().find(|m| m.name == plugin.name);
+                let launch = plugin.launch(manifest);
+                if launch.is_none() {
+                    errors.push(config.plugin_error(
+                        plugin,
+                        format!("plugin `{}` is not installed", plugin.name),
+                    ));
+                }
+                launch
+            })
+            .collect()
+    }
+
+    /// Returns the executables of the enabled plugins, so hosts can watch
+    /// them for changes, see [`watch`].
+    pub fn executables(&self) -> Vec<PathBuf> {
+        let mut executables: Vec<PathBuf> = self
+            .launches
+            .values()
+            .map(|launch| launch.executable.clone())
+            .collect();
+        executables.sort();
+        executables.dedup();
+        executables
+    }
+
+    /// Restarts the enabled plugins launched from the given executable,
+    /// e.g. after it was updated.
+    ///
+    /// All other plugins keep running.
+    pub fn executable_changed(&self, executable: &Path) {
+        for (name, _) in self
+            .launches
+            .iter()
+            .filter(|(_, launch)| launch.executable == executable)
+        {
+            self.supervisor.restart(name);
+        }
+    }
+
+    /// Shuts the installed plugin with the given name down.
+    pub fn disable(&mut self, name: &str) {
+        self.configured.remove(name);
+        if self.launches.remove(name).is_none() {
+            return;
+        }
+
+        self.supervisor.shutdown(name);
+    }
+
+    /// Gracefully shuts down every plugin, supervised or not.
+    pub fn shutdown_all(&self) {
+        for plugin in self.plugins.iter().filter(|p| p.process.is_none()) {
+            plugin.shutdown();
+        }
+        self.supervisor.shutdown_all();
+    }
+
+    /// Forwards a host event to the plugins interested in it.
+    ///
+    /// Keyboard events only reach the focused plugin. Escape dismisses all
+    /// popups.
+    pub fn handle_event(&mut self, event: iced::Event) {
+        match event {
+            iced::Event::Keyboard(event) => {
+                if let iced::keyboard::Event::KeyPressed {
+                    key: iced::keyboard::Key::Named(iced::keyboard::key::Named::Escape),
+                    ..
+                } = event
+                {
+                    self.dismiss_popups();
+                }
+
+                if let Some(plugin) = self.focused_plugin()
+                    && plugin.interests.keyboard
+                    && let Some(event) = KeyboardEvent::from_iced(event)
+                {
+                    plugin.notify(PluginEvent::Keyboard(event));
+                }
+            }
+            iced::Event::Window(iced::window::Event::Resized(size)) => {
+                self.surface.width = size.width;
+                self.surface.height = size.height;
+                self.broadcast_surface(PluginEvent::Resized(self.surface));
+            }
+            iced::Event::Window(iced::window::Event::Focused) => {
+                if let Some(plugin) = self.focused_plugin()
+                    && plugin.interests.keyboard
+                {
+                    plugin.notify(PluginEvent::Focus(true));
+                }
+            }
+            iced::Event::Window(iced::window::Event::Unfocused) => {
+                if let Some(plugin) = self.focused_plugin()
+                    && plugin.interests.keyboard
+                {
+                    plugin.notify(PluginEvent::Focus(false));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Sets the scale factor of the host surface.
+    pub fn set_scale_factor(&mut self, scale_factor: f32) {
+        if self.surface.scale_factor != scale_factor {
+            self.surface.scale_factor = scale_factor;
+            icons::set_scale(scale_factor.ceil() as u16);
+            self.broadcast_surface(PluginEvent::Resized(self.surface));
+        }
+    }
+
+    /// Notifies plugins that the host surface was shown or hidden.
+    pub fn set_visible(&mut self, visible: bool) {
+        self.broadcast_surface(PluginEvent::Visibility(visible));
+    }
+
+    /// Moves keyboard focus to the plugin with the given id.
+    pub fn focus(&mut self, id: Option<usize>) {
+        if self.focused == id {
+            return;
+        }
+
+        if let Some(plugin) = self.focused_plugin()
+            && plugin.interests.keyboard
+        {
+            plugin.notify(PluginEvent::Focus(false));
+        }
+        self.focused = id;
+        if let Some(plugin) = self.focused_plugin()
+            && plugin.interests.keyboard
+        {
+            plugin.notify(PluginEvent::Focus(true));
+        }
+    }
+
+    fn focused_plugin(&self) -> Option<&Plugin> {
+        self.focused
+            .and_then(|id| self.plugins.iter().find(|p| p.id == id))
+    }
+
+    fn broadcast_surface(&self, event: PluginEvent) {
+        for plugin in self.plugins.iter().filter(|p| p.interests.surface) {
+            plugin.notify(event.clone());
+        }
+    }
+
+    /// The outputs the host shows panels on.
+    pub fn outputs(&self) -> &[Output] {
+        &self.outputs
+    }
+
+    /// Sets the outputs the host shows panels on.
+    ///
+    /// Hosts call this whenever an output is added, removed or resized.
+    /// Plugins interested in outputs are told and send a view for every new
+    /// output, the views of removed outputs are dropped.
+    pub fn set_outputs(&mut self, outputs: Vec<Output>) {
+        if self.outputs == outputs {
+            return;
+        }
+
+        for plugin in &mut self.plugins {
+            plugin
+                .output_views
+                .retain(|name, _| outputs.iter().any(|o| o.name == *name));
+            if plugin.interests.outputs {
+                plugin.notify(PluginEvent::Outputs(outputs.clone()));
+            }
+        }
+        self.outputs = outputs;
+    }
+
+    /// Returns the open popups of all plugins.
+    pub fn popups(&self) -> impl Iterator<Item = (&Plugin, &Popup)> {
+        self.plugins
+            .iter()
+            .flat_map(|plugin| plugin.popups.iter().map(move |popup| (plugin, popup.as_ref())))
+    }
+
+    /// Returns the view of a popup of the plugin with the given id.
+    pub fn popup_view(
+        &self,
+        id: usize,
+        name: &str,
+    ) -> Option<iced::Element<'_, RuntimeMessage, iced::Theme, iced::Renderer>> {
+        let popup = self
+            .plugins
+            .iter()
+            .find(|p| p.id == id)?
+            .popups
+            .iter()
+            .find(|p| p.name == name)?;
+
+        Some(to_element(&popup.view).map(move |m| RuntimeMessage::Request(m, id)))
+    }
+
+    /// Closes a popup of the plugin with the given id and tells the plugin.
+    pub fn dismiss_popup(&mut self, id: usize, name: &str) {
+        if let Some(plugin) = self.plugins.iter_mut().find(|p| p.id == id)
+            && plugin.popups.iter().any(|p| p.name == name)
+        {
+            plugin.close_popup(name);
+            plugin.notify(PluginEvent::PopupDismissed(name.to_string()));
+        }
+    }
+
+    /// Closes the popups of all plugins.
+    pub fn dismiss_popups(&mut self) {
+        for plugin in &mut self.plugins {
+            plugin.popup_errors.clear();
+            for popup in mem::take(&mut plugin.popups) {
+                plugin.notify(PluginEvent::PopupDismissed(popup.name.clone()));
+            }
+        }
+    }
+
+    /// Returns the views of all plugins, ordered by slot and priority.
+    pub fn views(&self) -> Vec<iced::Element<'_, RuntimeMessage, iced::Theme, iced::Renderer>> {
+        self.layout
+            .slots()
+            .iter()
+            .flat_map(|slot| self.slot_views(slot))
+            .collect()
+    }
+
+    /// Returns the views of the plugins placed in the given slot, in order.
+    pub fn slot_views(
+        &self,
+        slot: &str,
+    ) -> Vec<iced::Element<'_, RuntimeMessage, iced::Theme, iced::Renderer>> {
+        self.plugins_in(slot)
+            .into_iter()
+            .filter_map(|p| p.element(None))
+            .collect()
+    }
+
+    /// Returns the views of all plugins on the named output, ordered by slot
+    /// and priority.
+    ///
+    /// Plugins without a view for the output show their main view.
+    pub fn views_on(
+        &self,
+        output: &str,
+    ) -> Vec<iced::Element<'_, RuntimeMessage, iced::Theme, iced::Renderer>> {
+        self.layout
+            .slots()
+            .iter()
+            .flat_map(|slot| self.slot_views_on(slot, output))
+            .collect()
+    }
+
+    /// Returns the views of the plugins placed in the given slot on the named
+    /// output, in order.
+    pub fn slot_views_on(
+        &self,
+        slot: &str,
+        output: &str,
+    ) -> Vec<iced::Element<'_, RuntimeMessage, iced::Theme, iced::Renderer>> {
+        self.plugins_in(slot)
+            .into_iter()
+            .filter_map(|p| p.element(Some(output)))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Plugin {
+    pub id: usize,
+    pub sender: UnboundedSender<PluginEvent>,
+    pub view: Option<Arc<Element>>,
+    /// The number of patches applied to `view` since it was last sent in full.
+    pub revision: u64,
+    pub interests: Interests,
+    /// The kinds of custom widgets the plugin announced.
+    pub widgets: Vec<String>,
+    pub pid: Option<u32>,
+    pub process: Option<ProcessHandle>,
+    /// The last error the plugin caused, if it has not recovered since.
+    pub error: Option<Error>,
+    /// Whether the plugin replied to the host in time.
+    pub responding: bool,
+    /// Where the plugin is shown on the panel.
+    pub placement: Placement,
+    /// The popups the plugin has open.
+    pub popups: Vec<Arc<Popup>>,
+    /// The errors in the views of open popups, by popup name.
+    pub popup_errors: HashMap<String, Error>,
+    /// The views of the plugin on single outputs, by output name.
+    pub output_views: HashMap<String, OutputView>,
+    /// Whether the host dropped the views of the plugin and ignores its
+    /// patches until it sends its main view in full again.
+    pub resyncing: bool,
+}
+
+/// The view of a [`Plugin`] on a single output.
+#[derive(Debug, Clone)]
+pub struct OutputView {
+    pub view: Arc<Element>,
+    /// The number of patches applied to `view` since it was last sent in full.
+    pub revision: u64,
+}
+
+impl Plugin {
+    /// The name the plugin is supervised as, if it was launched by the host.
+    pub fn name(&self) -> Option<&str> {
+        self.process.as_ref().map(|p| p.name())
+    }
+
+    /// The view of the plugin on the named output, or its main view.
+    fn element(
+        &self,
+        output: Option<&str>,
+    ) -> Option<iced::Element<'_, RuntimeMessage, iced::Theme, iced::Renderer>> {
+        let id = self.id;
+        let element = match output.and_then(|name| self.output_views.get_key_value(name)) {
+            Some((name, output)) => to_element(&output.view)
+                .map(move |m| RuntimeMessage::OutputRequest(m, id, name.clone())),
+            None => to_element(self.view.as_ref()?).map(move |m| RuntimeMessage::Request(m, id)),
+        };
+
+        let element = if self.interests.pointer {
+            mouse_area(element)
+                .on_enter(RuntimeMessage::Pointer(PointerEvent::Entered, id))
+                .on_move(move |point| {
+                    RuntimeMessage::Pointer(
+                        PointerEvent::Moved {
+                            x: point.x,
+                            y: point.y,
+                        },
+                        id,
+                    )
+                })
+                .on_exit(RuntimeMessage::Pointer(PointerEvent::Left, id))
+                .on_press(RuntimeMessage::Focus(id))
+                .into()
+        } else if self.interests.keyboard {
+            mouse_area(element)
+                .on_press(RuntimeMessage::Focus(id))
+                .into()
+        } else {
+            element
+        };
+
+        Some(if self.responding {
+            element
+        } else {
+            unresponsive(element)
+        })
+    }
+
+    /// Asks the plugin to exit.
+    ///
+    /// Supervised plugins that do not exit in time are terminated.
+    pub fn shutdown(&self) {
+        match &self.process {
+            Some(process) => process.shutdown(),
+            None => self.notify(PluginEvent::Shutdown),
+        }
+    }
+
+    /// Asks the plugin to exit and launches it again.
+    ///
+    /// Only supervised plugins can be restarted, others are left untouched.
+    pub fn restart(&self) {
+        if let Some(process) = &self.process {
+            process.restart();
+        }
+    }
+
+    fn close_popup(&mut self, name: &str) {
+        self.popups.retain(|p| p.name != name);
+        self.popup_errors.remove(name);
+    }
+
+    /// Drops the views of the plugin, which patches failed to apply to, and
+    /// asks it to send them in full again.
+    ///
+    /// Failed patches may have been applied in part, so the views are not
+    /// shown until then.
+    fn resync(&mut self) {
+        self.view = None;
+        self.output_views.clear();
+        self.resyncing = true;
+        self.notify(PluginEvent::Resync);
+    }
+
+    fn notify(&self, event: PluginEvent) {
+        // A closed channel means the plugin is shutting down, which is
+        // reported separately through `RuntimeMessage::Shutdown`.
+        let _ = self.sender.send(event);
+    }
+}
+
+/// Applies patches against the view with the given revision.
+///
+/// Returns `false` if they do not apply, in which case the view may have
+/// been patched in part and is unusable.
+fn apply(
+    view: &mut Arc<Element>,
+    revision: &mut u64,
+    base: u64,
+    patches: Arc<Vec<diff::Patch>>,
+) -> bool {
+    let applied = base == *revision
+        && Arc::get_mut(view)
+            .zip(Arc::into_inner(patches))
+            .is_some_and(|(view, patches)| diff::apply(view, patches).is_ok());
+    if applied {
+        *revision += 1;
+    }
+    applied
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::widgets::text::Text;
+
+    fn patches(old: &str, new: &str) -> Arc<Vec<diff::Patch>> {
+        let mut old: Element = Text::new(old).into();
+        Arc::new(diff::diff(&mut old, Text::new(new).into()))
+    }
+
+    fn plugin(id: usize) -> Plugin {
+        Plugin {
+            id,
+            sender: tokio::sync::mpsc::unbounded_channel().0,
+            view: None,
+            revision: 0,
+            interests: Interests::default(),
+            widgets: Vec::new(),
+            pid: None,
+            process: None,
+            error: None,
+            responding: true,
+            placement: Placement::default(),
+            popups: Vec::new(),
+            popup_errors: HashMap::new(),
+            output_views: HashMap::new(),
+            resyncing: false,
+        }
+    }
+
+    fn config(source: &str) -> Config {
+        Config::parse(source, "/etc/azalea/config.toml").unwrap()
+    }
+
+    #[test]
+    fn plugins_are_ordered_by_priority_then_id() {
+        let mut runtime = PluginRuntime::new();
+        for (id, slot, priority) in [
+            (1, "end", 5),
+            (2, "end", -1),
+            (3, "start", 0),
+            (4, "end", 5),
+        ] {
+            let mut plugin = plugin(id);
+            plugin.placement = Placement::new(slot, priority);
+            runtime.plugins.push(plugin);
+        }
+
+        let ids =
+            |slot: &str| -> Vec<usize> { runtime.plugins_in(slot).iter().map(|p| p.id).collect() };
+        assert_eq!(ids("end"), [2, 1, 4]);
+        assert_eq!(ids("start"), [3]);
+    }
+
+    #[test]
+    fn plugins_in_unknown_slots_are_not_shown() {
+        let mut runtime = PluginRuntime::new();
+        let mut hidden = plugin(1);
+        hidden.placement = Placement::new("nowhere", 0);
+        runtime.plugins.push(hidden);
+        runtime.plugins.push(plugin(2));
+
+        let shown: Vec<usize> = runtime
+            .layout
+            .slots()
+            .iter()
+            .flat_map(|slot| runtime.plugins_in(slot))
+            .map(|p| p.id)
+            .collect();
+        assert_eq!(shown, [2]);
+
+        runtime.move_plugin(1, Placement::new("end", 1));
+        assert_eq!(runtime.plugins_in("end").len(), 2);
+    }
+
+    #[test]
+    fn moved_plugins_keep_their_place_on_reload() {
+        let mut runtime = PluginRuntime::new();
+        runtime.plugins.push(plugin(1));
+        runtime.move_plugin(1, Placement::new("end", 2));
+
+        runtime.configure(&Config::default());
+
+        assert_eq!(runtime.plugins[0].placement, Placement::new("end", 2));
+    }
+
+    #[test]
+    fn configured_placements_apply_when_they_change() {
+        let mut runtime = PluginRuntime::new();
+        runtime.configure(&config("[[plugin]]\nname = \"clock\"\nslot = \"start\"\n"));
+        assert_eq!(
+            runtime.layout.placement("clock"),
+            Some(&Placement::new("start", 0))
+        );
+
+        // Moving a launched plugin places it by name.
+        runtime
+            .layout
+            .set_placement("clock", Placement::new("center", 0));
+        runtime.configure(&config(
+            "[[plugin]]\nname = \"clock\"\nslot = \"start\"\n\n[theme]\nbase = \"Nord\"\n",
+        ));
+        assert_eq!(
+            runtime.layout.placement("clock"),
+            Some(&Placement::new("center", 0))
+        );
+
+        runtime.configure(&config("[[plugin]]\nname = \"clock\"\nslot = \"end\"\n"));
+        assert_eq!(
+            runtime.layout.placement("clock"),
+            Some(&Placement::new("end", 0))
+        );
+
+        runtime.configure(&Config::default());
+        assert_eq!(runtime.layout.placement("clock"), None);
+    }
+
+    #[test]
+    fn patches_apply_to_their_revision() {
+        let mut view = Arc::new(Element::from(Text::new("a")));
+        let mut revision = 0;
+
+        assert!(apply(&mut view, &mut revision, 0, patches("a", "b")));
+        assert!(apply(&mut view, &mut revision, 1, patches("b", "c")));
+        assert_eq!(revision, 2);
+    }
+
+    #[test]
+    fn one_resync_is_sent_per_gap() {
+        let mut runtime = PluginRuntime::new();
+        let (sender, mut events) = tokio::sync::mpsc::unbounded_channel();
+        runtime.plugins.push(Plugin {
+            sender,
+            ..plugin(1)
+        });
+        let mut request = |request| {
+            runtime
+                .handle_plugin_message(RuntimeMessage::Request(request, 1), iced::Theme::default());
+        };
+
+        request(PluginRequest::View(Arc::new(Text::new("a").into())));
+        request(PluginRequest::Patch(1, patches("a", "b")));
+        request(PluginRequest::Patch(2, patches("b", "c")));
+        assert!(matches!(events.try_recv(), Ok(PluginEvent::Resync)));
+        assert!(events.try_recv().is_err());
+
+        // Patches apply again once the view was sent in full.
+        request(PluginRequest::View(Arc::new(Text::new("c").into())));
+        request(PluginRequest::Patch(0, patches("c", "d")));
+        assert!(events.try_recv().is_err());
+        assert_eq!(runtime.plugins[0].revision, 1);
+    }
+
+    #[test]
+    fn failed_patches_hide_the_view() {
+        let mut runtime = PluginRuntime::new();
+        let mut view = plugin(1);
+        view.view = Some(Arc::new(Text::new("a").into()));
+        view.revision = 3;
+        runtime.plugins.push(view);
+
+        runtime.handle_plugin_message(
+            RuntimeMessage::Request(PluginRequest::Patch(0, patches("a", "b")), 1),
+            iced::Theme::default(),
+        );
+
+        assert!(runtime.plugins[0].view.is_none());
+        assert!(runtime.plugins[0].resyncing);
+    }
+
+    #[test]
+    fn stale_revision_is_rejected() {
+        let mut view = Arc::new(Element::from(Text::new("a")));
+        let mut revision = 1;
+
+        assert!(!apply(&mut view, &mut revision, 0, patches("a", "b")));
+        assert!(!apply(&mut view, &mut revision, 2, patches("a", "b")));
+        assert_eq!(revision, 1);
+    }
+}
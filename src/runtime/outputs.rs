@@ -0,0 +1,151 @@
+//! Watching the outputs of the compositor, so hosts can show a panel on each
+//! of them and follow monitors as they are plugged in and out.
+//!
+//! Outputs are known by their name, e.g. `DP-1`, which compositors send from
+//! version 4 of `wl_output` on. Older outputs are not reported.
+use std::io;
+
+use iced::{
+    futures::{SinkExt, Stream},
+    stream,
+};
+use tokio::io::unix::AsyncFd;
+use wayland_client::{
+    Connection, Dispatch, QueueHandle,
+    backend::WaylandError,
+    protocol::{
+        wl_output::{self, WlOutput},
+        wl_registry::{self, WlRegistry},
+    },
+};
+
+use crate::on_tokio_runtime;
+
+/// Returns a stream of the names of the connected outputs, yielded whenever
+/// an output is added, removed or changed.
+///
+/// The stream ends if the compositor cannot be reached.
+pub fn watch() -> impl Stream<Item = Vec<String>> {
+    stream::channel(10, |mut tx| {
+        on_tokio_runtime(async move {
+            let Ok(connection) = Connection::connect_to_env() else {
+                return;
+            };
+            let mut queue = connection.new_event_queue();
+            connection.display().get_registry(&queue.handle(), ());
+
+            let Ok(fd) = connection.backend().poll_fd().try_clone_to_owned() else {
+                return;
+            };
+            let Ok(socket) = AsyncFd::new(fd) else {
+                return;
+            };
+
+            let mut outputs = Outputs::default();
+            loop {
+                if queue.dispatch_pending(&mut outputs).is_err() {
+                    return;
+                }
+                if outputs.changed {
+                    outputs.changed = false;
+                    if tx.send(outputs.names()).await.is_err() {
+                        return;
+                    }
+                }
+                if queue.flush().is_err() {
+                    return;
+                }
+
+                // The read guard cannot be held across an await, so it is
+                // only taken once the socket is readable.
+                let Ok(mut ready) = socket.readable().await else {
+                    return;
+                };
+                // Events may have been queued by another read since.
+                let Some(guard) = queue.prepare_read() else {
+                    continue;
+                };
+                match guard.read() {
+                    Ok(_) => {}
+                    Err(WaylandError::Io(error)) if error.kind() == io::ErrorKind::WouldBlock => {
+                        ready.clear_ready();
+                    }
+                    Err(_) => return,
+                }
+            }
+        })
+    })
+}
+
+#[derive(Debug, Default)]
+struct Outputs {
+    /// The bound outputs with the name of their global, and their own name
+    /// once the compositor sent it.
+    outputs: Vec<(u32, WlOutput, Option<String>)>,
+    /// Whether the outputs changed since they were last reported.
+    changed: bool,
+}
+
+impl Outputs {
+    fn names(&self) -> Vec<String> {
+        self.outputs
+            .iter()
+            .filter_map(|(_, _, name)| name.clone())
+            .collect()
+    }
+}
+
+impl Dispatch<WlRegistry, ()> for Outputs {
+    fn event(
+        state: &mut Self,
+        registry: &WlRegistry,
+        event: wl_registry::Event,
+        _: &(),
+        _: &Connection,
+        queue: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_registry::Event::Global {
+                name,
+                interface,
+                version,
+            } if interface == "wl_output" && version >= 4 => {
+                let output = registry.bind(name, 4, queue, ());
+                state.outputs.push((name, output, None));
+            }
+            wl_registry::Event::GlobalRemove { name } => {
+                if let Some(i) = state
+                    .outputs
+                    .iter()
+                    .position(|(global, ..)| *global == name)
+                {
+                    let (_, output, _) = state.outputs.remove(i);
+                    output.release();
+                    state.changed = true;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WlOutput, ()> for Outputs {
+    fn event(
+        state: &mut Self,
+        output: &WlOutput,
+        event: wl_output::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let Some((_, _, name)) = state.outputs.iter_mut().find(|(_, o, _)| o == output) else {
+            return;
+        };
+        match event {
+            wl_output::Event::Name { name: new } => *name = Some(new),
+            // The properties of an output are complete once `done` is sent.
+            wl_output::Event::Done => state.changed = true,
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,707 @@
+//! Launching and supervising plugin processes.
+//!
+//! The [`Supervisor`] spawns plugin executables, passes them the socket path
+//! of the host and keeps track of their process ids. Plugins that exit on
+//! their own are restarted with an exponential backoff, until they crash too
+//! often in a short period of time.
+//!
+//! Plugins are stopped by sending them [`PluginEvent::Shutdown`] through
+//! their connection first, see [`Supervisor::attach`], so they can exit
+//! cleanly before they are terminated.
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    process::{ExitStatus, Stdio},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use tokio::{
+    process::{Child, Command},
+    sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel},
+};
+
+use crate::{PluginEvent, SOCKET_ENV, socket_path, tokio_runtime};
+
+/// The environment variable holding the name a plugin was launched as.
+pub const NAME_ENV: &str = "AZALEA_PLUGIN";
+
+/// How a plugin executable is launched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Launch {
+    /// The name the plugin is supervised as.
+    pub name: String,
+    /// The path of the executable.
+    pub executable: PathBuf,
+    /// The arguments passed to the executable.
+    pub args: Vec<String>,
+    /// Additional environment variables for the process.
+    pub env: Vec<(String, String)>,
+}
+
+impl Launch {
+    /// Creates a [`Launch`] for the given executable.
+    pub fn new(name: impl Into<String>, executable: impl Into<PathBuf>) -> Self {
+        Self {
+            name: name.into(),
+            executable: executable.into(),
+            args: Vec::new(),
+            env: Vec::new(),
+        }
+    }
+
+    /// Adds an argument.
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Adds an environment variable.
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+}
+
+/// The timeouts and restart limits of a [`Supervisor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Policy {
+    /// How long a plugin may take to exit before it is sent `SIGTERM`, and
+    /// then `SIGKILL`.
+    pub grace_period: Duration,
+    /// The delay before the first restart of a crashed plugin.
+    pub initial_backoff: Duration,
+    /// The longest delay between restarts.
+    pub max_backoff: Duration,
+    /// How many crashes are tolerated within [`Policy::restart_window`]
+    /// before the plugin is given up on.
+    pub max_restarts: u32,
+    /// The period crashes are counted in.
+    pub restart_window: Duration,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            grace_period: Duration::from_secs(2),
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            max_restarts: 5,
+            restart_window: Duration::from_secs(60),
+        }
+    }
+}
+
+/// The state of a supervised process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessState {
+    /// The process is running with the given pid.
+    Running(u32),
+    /// The process crashed and will be restarted after the given delay.
+    Restarting(Duration),
+    /// The process crashed too often and was given up on.
+    Failed,
+    /// The process was asked to shut down and has not exited yet.
+    Stopping,
+    /// The process was shut down.
+    Stopped,
+}
+
+#[derive(Debug)]
+enum Control {
+    Shutdown,
+    Restart,
+    Relaunch(Launch),
+}
+
+/// A handle to a single supervised plugin.
+#[derive(Debug, Clone)]
+pub struct ProcessHandle {
+    name: String,
+    control: UnboundedSender<Control>,
+    processes: Arc<Mutex<HashMap<String, Process>>>,
+}
+
+impl ProcessHandle {
+    /// The name the plugin is supervised as.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Shuts the plugin down, see [`Supervisor::shutdown`].
+    pub fn shutdown(&self) {
+        self.send(Control::Shutdown);
+    }
+
+    /// Restarts the plugin, see [`Supervisor::restart`].
+    pub fn restart(&self) {
+        self.send(Control::Restart);
+    }
+
+    fn send(&self, control: Control) {
+        let mut processes = self.processes.lock().unwrap();
+        // The plugin may have been spawned again under the same name since.
+        if let Some(process) = processes.get_mut(&self.name)
+            && process.control.same_channel(&self.control)
+        {
+            process.send(control);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Process {
+    control: UnboundedSender<Control>,
+    state: ProcessState,
+    /// The connection of the running process, once it connected.
+    connection: Option<UnboundedSender<PluginEvent>>,
+}
+
+impl Process {
+    /// Sends a control message to the task supervising the process.
+    ///
+    /// The state is updated along with it, so a plugin that is spawned again
+    /// while it is being shut down is not mistaken for a running one.
+    fn send(&mut self, control: Control) {
+        match (&control, self.state) {
+            (Control::Shutdown, ProcessState::Stopped) => {}
+            (Control::Shutdown, _) => self.state = ProcessState::Stopping,
+            (Control::Restart | Control::Relaunch(_), ProcessState::Stopping) => {
+                self.state = ProcessState::Restarting(Duration::ZERO);
+            }
+            _ => {}
+        }
+        let _ = self.control.send(control);
+    }
+
+    fn handle(
+        &self,
+        name: &str,
+        processes: &Arc<Mutex<HashMap<String, Process>>>,
+    ) -> ProcessHandle {
+        ProcessHandle {
+            name: name.to_string(),
+            control: self.control.clone(),
+            processes: processes.clone(),
+        }
+    }
+}
+
+/// Spawns plugin executables and restarts them when they crash.
+#[derive(Debug, Clone)]
+pub struct Supervisor {
+    socket: PathBuf,
+    policy: Policy,
+    processes: Arc<Mutex<HashMap<String, Process>>>,
+}
+
+impl Default for Supervisor {
+    fn default() -> Self {
+        Self::new(socket_path(), Policy::default())
+    }
+}
+
+impl Supervisor {
+    /// Creates a [`Supervisor`] launching plugins against the given socket.
+    pub fn new(socket: impl Into<PathBuf>, policy: Policy) -> Self {
+        Self {
+            socket: socket.into(),
+            policy,
+            processes: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Spawns a plugin and keeps it running until it is shut down.
+    ///
+    /// A plugin that is already supervised under the same name is left
+    /// untouched, unless it is being shut down. It is then started again
+    /// with the given [`Launch`] once it exited.
+    pub fn spawn(&self, launch: Launch) {
+        let mut processes = self.processes.lock().unwrap();
+        if let Some(process) = processes.get_mut(&launch.name) {
+            match process.state {
+                ProcessState::Stopped => {}
+                ProcessState::Stopping => return process.send(Control::Relaunch(launch)),
+                _ => return,
+            }
+        }
+
+        let (control, receiver) = unbounded_channel();
+        processes.insert(
+            launch.name.clone(),
+            Process {
+                control,
+                state: ProcessState::Restarting(Duration::ZERO),
+                connection: None,
+            },
+        );
+
+        let task = Task {
+            launch,
+            socket: self.socket.clone(),
+            policy: self.policy,
+            processes: self.processes.clone(),
+        };
+        tokio_runtime().spawn(task.run(receiver));
+    }
+
+    /// Shuts the plugin with the given name down.
+    ///
+    /// The plugin is sent [`PluginEvent::Shutdown`] if it is connected, and
+    /// given [`Policy::grace_period`] to exit on its own before it is sent
+    /// `SIGTERM`, and the same period again before `SIGKILL`.
+    pub fn shutdown(&self, name: &str) {
+        self.send(name, Control::Shutdown);
+    }
+
+    /// Shuts the plugin with the given name down and starts it again.
+    ///
+    /// This also revives plugins that were given up on.
+    pub fn restart(&self, name: &str) {
+        self.send(name, Control::Restart);
+    }
+
+    /// Restarts the plugin with the given name with a different [`Launch`],
+    /// e.g. after its arguments were changed.
+    ///
+    /// Plugins that are not supervised yet are spawned.
+    pub fn relaunch(&self, launch: Launch) {
+        let mut processes = self.processes.lock().unwrap();
+        match processes.get_mut(&launch.name) {
+            Some(process) if process.state != ProcessState::Stopped => {
+                process.send(Control::Relaunch(launch));
+            }
+            _ => {
+                drop(processes);
+                self.spawn(launch);
+            }
+        }
+    }
+
+    /// Shuts every supervised plugin down.
+    pub fn shutdown_all(&self) {
+        for process in self.processes.lock().unwrap().values_mut() {
+            process.send(Control::Shutdown);
+        }
+    }
+
+    /// Returns the state of the plugin with the given name.
+    pub fn state(&self, name: &str) -> Option<ProcessState> {
+        self.processes.lock().unwrap().get(name).map(|p| p.state)
+    }
+
+    /// Returns the names and states of all supervised plugins.
+    pub fn processes(&self) -> Vec<(String, ProcessState)> {
+        self.processes
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, process)| (name.clone(), process.state))
+            .collect()
+    }
+
+    /// Returns a handle to the supervised plugin running with the given pid.
+    pub fn handle(&self, pid: u32) -> Option<ProcessHandle> {
+        self.processes
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(_, process)| process.state == ProcessState::Running(pid))
+            .map(|(name, process)| process.handle(name, &self.processes))
+    }
+
+    /// Attaches the connection of the supervised plugin running with the
+    /// given pid, so it is asked to exit through it before it is terminated.
+    ///
+    /// Returns a handle to the plugin, like [`Supervisor::handle`].
+    pub fn attach(
+        &self,
+        pid: u32,
+        connection: UnboundedSender<PluginEvent>,
+    ) -> Option<ProcessHandle> {
+        let mut processes = self.processes.lock().unwrap();
+        let (name, process) = processes
+            .iter_mut()
+            .find(|(_, process)| process.state == ProcessState::Running(pid))?;
+        process.connection = Some(connection);
+
+        Some(process.handle(name, &self.processes))
+    }
+
+    fn send(&self, name: &str, control: Control) {
+        if let Some(process) = self.processes.lock().unwrap().get_mut(name) {
+            process.send(control);
+        }
+    }
+}
+
+struct Task {
+    launch: Launch,
+    socket: PathBuf,
+    policy: Policy,
+    processes: Arc<Mutex<HashMap<String, Process>>>,
+}
+
+impl Task {
+    async fn run(mut self, mut control: UnboundedReceiver<Control>) {
+        while self.supervise(&mut control).await {}
+    }
+
+    /// Keeps the plugin running until it is shut down, returning whether it
+    /// is started again by a message that raced the shutdown.
+    async fn supervise(&mut self, control: &mut UnboundedReceiver<Control>) -> bool {
+        let mut backoff = Backoff::new(self.policy);
+
+        loop {
+            let started = Instant::now();
+            let exit = match self.spawn() {
+                Ok(mut child) => {
+                    self.set_state(ProcessState::Running(child.id().unwrap_or_default()));
+                    tokio::select! {
+                        status = child.wait() => status.ok(),
+                        message = control.recv() => {
+                            self.terminate(&mut child).await;
+                            if !self.control(message, control) {
+                                break;
+                            }
+                            backoff.reset();
+                            continue;
+                        }
+                    }
+                }
+                Err(_) => None,
+            };
+
+            if exit.is_some_and(|status| status.success()) {
+                // Plugins exit cleanly when they are asked to, which races
+                // with a restart requested along with it. The restart wins.
+                if !self.control(control.try_recv().ok(), control) {
+                    break;
+                }
+                backoff.reset();
+                continue;
+            }
+
+            let Some(delay) = backoff.crashed(started, Instant::now()) else {
+                self.set_state(ProcessState::Failed);
+                if !self.control(control.recv().await, control) {
+                    break;
+                }
+                backoff.reset();
+                continue;
+            };
+
+            self.set_state(ProcessState::Restarting(delay));
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                message = control.recv() => {
+                    if !self.control(message, control) {
+                        break;
+                    }
+                    backoff.reset();
+                }
+            }
+        }
+
+        self.stop(control)
+    }
+
+    /// Handles a control message and the ones queued after it, returning
+    /// whether the plugin is started again.
+    ///
+    /// The last message wins, so a plugin that is spawned again while it is
+    /// being shut down is started once it exited.
+    fn control(
+        &mut self,
+        message: Option<Control>,
+        control: &mut UnboundedReceiver<Control>,
+    ) -> bool {
+        let mut start = false;
+        let mut next = message;
+        while let Some(message) = next {
+            start = match message {
+                Control::Restart => true,
+                Control::Relaunch(launch) => {
+                    self.launch = launch;
+                    true
+                }
+                Control::Shutdown => false,
+            };
+            next = control.try_recv().ok();
+        }
+        start
+    }
+
+    /// Marks the plugin as stopped, unless a message raced the shutdown,
+    /// returning whether the plugin is started again.
+    ///
+    /// Messages are sent with the processes locked, so they either arrive
+    /// before the plugin is marked as stopped, or see it stopped and spawn
+    /// it anew.
+    fn stop(&mut self, control: &mut UnboundedReceiver<Control>) -> bool {
+        loop {
+            let mut processes = self.processes.lock().unwrap();
+            let Ok(message) = control.try_recv() else {
+                if let Some(process) = processes.get_mut(&self.launch.name) {
+                    process.state = ProcessState::Stopped;
+                    process.connection = None;
+                }
+                return false;
+            };
+            drop(processes);
+
+            if self.control(Some(message), control) {
+                return true;
+            }
+        }
+    }
+
+    fn spawn(&self) -> std::io::Result<Child> {
+        Command::new(&self.launch.executable)
+            .args(&self.launch.args)
+            .envs(self.launch.env.iter().map(|(k, v)| (k, v)))
+            .env(SOCKET_ENV, &self.socket)
+            .env(NAME_ENV, &self.launch.name)
+            .stdin(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+    }
+
+    /// Asks the child to exit and waits for it, escalating to `SIGTERM` and
+    /// `SIGKILL`.
+    async fn terminate(&self, child: &mut Child) -> Option<ExitStatus> {
+        let connection = self
+            .processes
+            .lock()
+            .unwrap()
+            .get_mut(&self.launch.name)
+            .and_then(|process| process.connection.take());
+        if let Some(connection) = connection {
+            let _ = connection.send(PluginEvent::Shutdown);
+        }
+
+        let grace_period = self.policy.grace_period;
+        if let Ok(status) = tokio::time::timeout(grace_period, child.wait()).await {
+            return status.ok();
+        }
+
+        if let Some(pid) = child.id() {
+            // SAFETY: `kill` has no memory safety requirements, and the pid
+            // belongs to a child that has not been reaped yet.
+            unsafe {
+                libc::kill(pid as libc::pid_t, libc::SIGTERM);
+            }
+            if let Ok(status) = tokio::time::timeout(grace_period, child.wait()).await {
+                return status.ok();
+            }
+        }
+
+        let _ = child.kill().await;
+        child.wait().await.ok()
+    }
+
+    fn set_state(&self, state: ProcessState) {
+        if let Some(process) = self.processes.lock().unwrap().get_mut(&self.launch.name) {
+            // The plugin is stopping until the shutdown is handled.
+            if process.state == ProcessState::Stopping {
+                return;
+            }
+            // A connection belongs to the process it was attached for.
+            if process.state != state {
+                process.connection = None;
+            }
+            process.state = state;
+        }
+    }
+}
+
+/// The delays between the restarts of a crashing plugin.
+#[derive(Debug)]
+struct Backoff {
+    policy: Policy,
+    /// When the plugin crashed within [`Policy::restart_window`].
+    crashes: Vec<Instant>,
+    /// The delay before the next restart.
+    delay: Duration,
+}
+
+impl Backoff {
+    fn new(policy: Policy) -> Self {
+        Self {
+            policy,
+            crashes: Vec::new(),
+            delay: policy.initial_backoff,
+        }
+    }
+
+    /// Forgets earlier crashes, after the plugin was started on purpose.
+    fn reset(&mut self) {
+        self.crashes.clear();
+        self.delay = self.policy.initial_backoff;
+    }
+
+    /// Records a crash of the plugin started at `started`, returning how long
+    /// to wait before restarting it, or `None` if it crashed too often.
+    fn crashed(&mut self, started: Instant, now: Instant) -> Option<Duration> {
+        // A plugin that ran for a while starts over with a short delay.
+        if now.duration_since(started) > self.policy.restart_window {
+            self.delay = self.policy.initial_backoff;
+        }
+        self.crashes
+            .retain(|crash| now.duration_since(*crash) <= self.policy.restart_window);
+        self.crashes.push(now);
+        if self.crashes.len() as u32 > self.policy.max_restarts {
+            return None;
+        }
+
+        let delay = self.delay;
+        self.delay = (delay * 2).min(self.policy.max_backoff);
+        Some(delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    const POLICY: Policy = Policy {
+        grace_period: Duration::from_millis(100),
+        initial_backoff: Duration::from_millis(10),
+        max_backoff: Duration::from_millis(40),
+        max_restarts: 3,
+        restart_window: Duration::from_secs(60),
+    };
+
+    /// A plugin that ignores `SIGTERM`, so only `SIGKILL` stops it.
+    fn stubborn(name: &str) -> Launch {
+        Launch::new(name, "/bin/sh")
+            .arg("-c")
+            .arg("trap '' TERM; exec sleep 30")
+    }
+
+    /// Waits until the plugin with the given name is in a state accepted by
+    /// `f`, and returns that state.
+    fn wait_for(
+        supervisor: &Supervisor,
+        name: &str,
+        f: impl Fn(ProcessState) -> bool,
+    ) -> ProcessState {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            if let Some(state) = supervisor.state(name)
+                && f(state)
+            {
+                return state;
+            }
+            assert!(
+                Instant::now() < deadline,
+                "`{name}` is {:?}",
+                supervisor.state(name)
+            );
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    fn running(supervisor: &Supervisor, name: &str) -> u32 {
+        match wait_for(supervisor, name, |s| matches!(s, ProcessState::Running(_))) {
+            ProcessState::Running(pid) => pid,
+            _ => unreachable!(),
+        }
+    }
+
+    fn alive(pid: u32) -> bool {
+        // SAFETY: signal 0 only checks whether the process exists.
+        unsafe { libc::kill(pid as libc::pid_t, 0) == 0 }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let mut backoff = Backoff::new(Policy {
+            max_restarts: 10,
+            ..POLICY
+        });
+        let now = Instant::now();
+
+        let delays: Vec<u64> = (0..4)
+            .filter_map(|_| backoff.crashed(now, now))
+            .map(|delay| delay.as_millis() as u64)
+            .collect();
+        assert_eq!(delays, [10, 20, 40, 40]);
+    }
+
+    #[test]
+    fn backoff_starts_over_after_a_reset_or_a_long_run() {
+        let mut backoff = Backoff::new(POLICY);
+        let now = Instant::now();
+        backoff.crashed(now, now);
+        backoff.crashed(now, now);
+
+        backoff.reset();
+        assert_eq!(backoff.crashed(now, now), Some(POLICY.initial_backoff));
+        assert_eq!(backoff.crashed(now, now), Some(POLICY.initial_backoff * 2));
+
+        let later = now + POLICY.restart_window * 2;
+        assert_eq!(backoff.crashed(now, later), Some(POLICY.initial_backoff));
+    }
+
+    #[test]
+    fn only_crashes_within_the_window_count() {
+        let mut backoff = Backoff::new(POLICY);
+        let now = Instant::now();
+        for _ in 0..POLICY.max_restarts {
+            assert!(backoff.crashed(now, now).is_some());
+        }
+        assert_eq!(backoff.crashed(now, now), None);
+
+        let later = now + POLICY.restart_window * 2;
+        assert!(backoff.crashed(later, later).is_some());
+    }
+
+    #[test]
+    fn crash_loops_are_given_up_on() {
+        let supervisor = Supervisor::new("/nonexistent", POLICY);
+        supervisor.spawn(Launch::new("crash", "/bin/sh").arg("-c").arg("exit 1"));
+
+        wait_for(&supervisor, "crash", |s| s == ProcessState::Failed);
+
+        supervisor.shutdown("crash");
+        wait_for(&supervisor, "crash", |s| s == ProcessState::Stopped);
+    }
+
+    #[test]
+    fn plugins_ignoring_sigterm_are_killed() {
+        let supervisor = Supervisor::new("/nonexistent", POLICY);
+        supervisor.spawn(stubborn("stubborn"));
+        let pid = running(&supervisor, "stubborn");
+        // Give the shell time to ignore `SIGTERM`.
+        thread::sleep(Duration::from_millis(50));
+
+        let started = Instant::now();
+        supervisor.shutdown("stubborn");
+        assert_eq!(supervisor.state("stubborn"), Some(ProcessState::Stopping));
+        wait_for(&supervisor, "stubborn", |s| s == ProcessState::Stopped);
+
+        assert!(started.elapsed() >= POLICY.grace_period * 2);
+        assert!(!alive(pid));
+    }
+
+    #[test]
+    fn plugins_spawned_while_stopping_are_started_again() {
+        let supervisor = Supervisor::new("/nonexistent", POLICY);
+        supervisor.spawn(stubborn("reload"));
+        let pid = running(&supervisor, "reload");
+        thread::sleep(Duration::from_millis(50));
+
+        supervisor.shutdown("reload");
+        supervisor.spawn(stubborn("reload"));
+
+        let restarted = running(&supervisor, "reload");
+        assert_ne!(restarted, pid);
+        assert!(!alive(pid));
+
+        supervisor.shutdown("reload");
+        wait_for(&supervisor, "reload", |s| s == ProcessState::Stopped);
+    }
+}
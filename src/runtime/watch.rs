@@ -0,0 +1,213 @@
+//! Watching files for changes, such as the host configuration or plugin
+//! executables.
+//!
+//! Files are watched through their parent directories with inotify, so
+//! files that are replaced by renaming a new file over them, as editors and
+//! package managers do, keep being watched.
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::{CString, OsStr, OsString},
+    io, mem,
+    os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd},
+        unix::ffi::OsStrExt,
+    },
+    path::PathBuf,
+    ptr,
+    time::Duration,
+};
+
+use iced::{
+    futures::{SinkExt, Stream},
+    stream,
+};
+use tokio::io::unix::AsyncFd;
+
+use crate::on_tokio_runtime;
+
+/// How long a file has to stay unchanged before the change is reported, so
+/// a file that is written in several steps is reported once.
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// The events that count as a change of a watched file.
+const MASK: u32 = libc::IN_CLOSE_WRITE | libc::IN_MOVED_TO | libc::IN_CREATE | libc::IN_ATTRIB;
+
+/// Returns a stream of the given files as they are changed.
+///
+/// Files in directories that do not exist are not watched. The stream ends
+/// if the files cannot be watched at all.
+pub fn watch(paths: Vec<PathBuf>) -> impl Stream<Item = PathBuf> {
+    stream::channel(100, move |mut tx| {
+        on_tokio_runtime(async move {
+            let Ok(inotify) = Inotify::new() else {
+                return;
+            };
+
+            let mut directories: HashMap<i32, PathBuf> = HashMap::new();
+            for path in &paths {
+                if let Some(directory) = path.parent()
+                    && !directories.values().any(|d| d == directory)
+                    && let Ok(wd) = inotify.add(directory.as_os_str())
+                {
+                    directories.insert(wd, directory.to_path_buf());
+                }
+            }
+
+            loop {
+                let mut changed = HashSet::new();
+                let mut collect = |events: Vec<(i32, OsString)>| {
+                    for (wd, name) in events {
+                        if let Some(directory) = directories.get(&wd) {
+                            let path = directory.join(name);
+                            if paths.contains(&path) {
+                                changed.insert(path);
+                            }
+                        }
+                    }
+                };
+
+                match inotify.read().await {
+                    Ok(events) => collect(events),
+                    Err(_) => return,
+                }
+                while let Ok(events) = tokio::time::timeout(DEBOUNCE, inotify.read()).await {
+                    match events {
+                        Ok(events) => collect(events),
+                        Err(_) => return,
+                    }
+                }
+
+                for path in changed {
+                    if tx.send(path).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        })
+    })
+}
+
+/// An inotify instance.
+struct Inotify {
+    fd: AsyncFd<OwnedFd>,
+}
+
+impl Inotify {
+    fn new() -> io::Result<Self> {
+        // SAFETY: `inotify_init1` has no preconditions.
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        // SAFETY: `fd` is a new file descriptor nothing else owns.
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        Ok(Inotify {
+            fd: AsyncFd::new(fd)?,
+        })
+    }
+
+    /// Watches the entries of a directory, returning the watch descriptor.
+    fn add(&self, directory: &OsStr) -> io::Result<i32> {
+        let directory = CString::new(directory.as_bytes())?;
+        // SAFETY: `directory` is a valid C string for the duration of the
+        // call.
+        let wd = unsafe {
+            libc::inotify_add_watch(self.fd.as_raw_fd(), directory.as_ptr(), MASK)
+        };
+        if wd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(wd)
+    }
+
+    /// Reads the next events, as the watch descriptor and the name of the
+    /// entry they are about.
+    async fn read(&self) -> io::Result<Vec<(i32, OsString)>> {
+        let mut buffer = [0u8; 4096];
+        loop {
+            let mut guard = self.fd.readable().await?;
+            let read = guard.try_io(|fd| {
+                // SAFETY: `buffer` is valid for writes of its length.
+                let read = unsafe {
+                    libc::read(fd.as_raw_fd(), buffer.as_mut_ptr().cast(), buffer.len())
+                };
+                if read < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(read as usize)
+            });
+
+            if let Ok(read) = read {
+                return read.map(|len| parse(&buffer[..len]));
+            }
+        }
+    }
+}
+
+/// Parses the events read from an inotify instance.
+fn parse(mut buffer: &[u8]) -> Vec<(i32, OsString)> {
+    let header = mem::size_of::<libc::inotify_event>();
+
+    let mut events = Vec::new();
+    while buffer.len() >= header {
+        // SAFETY: The buffer holds at least one event header, which may not
+        // be aligned.
+        let event: libc::inotify_event = unsafe { ptr::read_unaligned(buffer.as_ptr().cast()) };
+        let end = (header + event.len as usize).min(buffer.len());
+        // Names are padded with nul bytes.
+        let name = buffer[header..end]
+            .split(|&byte| byte == 0)
+            .next()
+            .unwrap_or_default();
+        events.push((event.wd, OsStr::from_bytes(name).to_os_string()));
+        buffer = &buffer[end..];
+    }
+
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encodes an event as the kernel does, with the name padded by nul
+    /// bytes.
+    fn event(wd: i32, name: &str, padding: usize) -> Vec<u8> {
+        let len = if name.is_empty() { 0 } else { name.len() + padding };
+        let mut bytes = Vec::new();
+        bytes.extend(wd.to_ne_bytes());
+        bytes.extend(libc::IN_CLOSE_WRITE.to_ne_bytes());
+        bytes.extend(0u32.to_ne_bytes());
+        bytes.extend((len as u32).to_ne_bytes());
+        bytes.extend(name.as_bytes());
+        bytes.resize(bytes.len() + len - name.len(), 0);
+        bytes
+    }
+
+    #[test]
+    fn events_are_parsed_with_their_names() {
+        let mut buffer = event(1, "config.toml", 5);
+        buffer.extend(event(2, "", 0));
+        buffer.extend(event(3, "clock", 3));
+
+        assert_eq!(
+            parse(&buffer),
+            vec![
+                (1, OsString::from("config.toml")),
+                (2, OsString::new()),
+                (3, OsString::from("clock")),
+            ]
+        );
+    }
+
+    #[test]
+    fn truncated_events_are_not_read_past_the_buffer() {
+        let buffer = event(1, "config.toml", 5);
+        let header = mem::size_of::<libc::inotify_event>();
+
+        assert_eq!(parse(&buffer[..header + 6]), vec![(1, OsString::from("config"))]);
+        assert_eq!(parse(&buffer[..header - 1]), vec![]);
+    }
+}
@@ -25,7 +25,6 @@ use iced::border::{self, Border};
 use iced::theme::palette;
 use iced::widget::button;
 use iced::{Background, Color, Length, Padding, Shadow, Size, Theme};
-use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
 use crate::widgets::element::to_element;
@@ -71,9 +70,9 @@ use crate::{PluginRequest, Element};
 /// ```
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct Button {
-    content: Element,
-    on_press: Option<Vec<u8>>,
+pub struct Button<Message = Vec<u8>> {
+    content: Element<Message>,
+    on_press: Option<Message>,
     #[serde(with = "LengthDef")]
     width: Length,
     #[serde(with = "LengthDef")]
@@ -81,12 +80,12 @@ pub struct Button {
     #[serde(with = "PaddingDef")]
     padding: Padding,
     clip: bool,
-    class: Option<StateStyle>,
+    class: Class,
 }
 
-impl Button {
+impl<Message> Button<Message> {
     /// Creates a new [`Button`] with the given content.
-    pub fn new(content: impl Into<Element>) -> Self {
+    pub fn new(content: impl Into<Element<Message>>) -> Self {
         let content = content.into();
         let size = content.as_widget().size_hint();
 
@@ -97,7 +96,7 @@ impl Button {
             height: size.height.fluid(),
             padding: DEFAULT_PADDING,
             clip: false,
-            class: None,
+            class: Class::default(),
         }
     }
 
@@ -122,12 +121,8 @@ impl Button {
     /// Sets the message that will be produced when the [`Button`] is pressed.
     ///
     /// Unless `on_press` is called, the [`Button`] will be disabled.
-    pub fn on_press<Message>(mut self, on_press: Message) -> Self
-    where
-        Message: Send + Sync + Serialize + DeserializeOwned,
-    {
-        self.on_press =
-            Some(bincode::serde::encode_to_vec(on_press, bincode::config::standard()).unwrap());
+    pub fn on_press(mut self, on_press: Message) -> Self {
+        self.on_press = Some(on_press);
         self
     }
 
@@ -139,12 +134,8 @@ impl Button {
     /// This closure will only be called when the [`Button`] is actually pressed and,
     /// therefore, this method is useful to reduce overhead if creating the resulting
     /// message is slow.
-    pub fn on_press_with<Message>(mut self, on_press: impl Fn() -> Message) -> Self
-    where
-        Message: Send + Sync + Serialize + DeserializeOwned,
-    {
-        self.on_press =
-            Some(bincode::serde::encode_to_vec(on_press(), bincode::config::standard()).unwrap());
+    pub fn on_press_with(mut self, on_press: impl Fn() -> Message) -> Self {
+        self.on_press = Some(on_press());
         self
     }
 
@@ -152,12 +143,8 @@ impl Button {
     /// if `Some`.
     ///
     /// If `None`, the [`Button`] will be disabled.
-    pub fn on_press_maybe<Message>(mut self, on_press: Option<Message>) -> Self
-    where
-        Message: Send + Sync + Serialize + DeserializeOwned,
-    {
-        self.on_press =
-            Some(bincode::serde::encode_to_vec(on_press, bincode::config::standard()).unwrap());
+    pub fn on_press_maybe(mut self, on_press: Option<Message>) -> Self {
+        self.on_press = on_press;
         self
     }
 
@@ -171,15 +158,30 @@ impl Button {
     /// Sets the style of the [`Button`].
     #[must_use]
     pub fn style(mut self, style: StateStyle) -> Self {
-        self.class = Some(style);
+        self.class = Class::Style(Box::new(style));
         self
     }
 
-    /// Sets the style class of the [`Button`]
-    pub fn class(mut self, class: StateStyle) -> Self {
-        self.class = Some(class);
+    /// Sets the style class of the [`Button`].
+    ///
+    /// Classes other than [`Class::Style`] are resolved against the theme of
+    /// the host.
+    pub fn class(mut self, class: impl Into<Class>) -> Self {
+        self.class = class.into();
         self
     }
+
+    pub(crate) fn filter_map<B>(self, f: &dyn Fn(Message) -> Option<B>) -> Button<B> {
+        Button {
+            content: self.content.filter_map(f),
+            on_press: self.on_press.and_then(f),
+            width: self.width,
+            height: self.height,
+            padding: self.padding,
+            clip: self.clip,
+            class: self.class,
+        }
+    }
 }
 
 #[expect(dead_code)]
@@ -188,44 +190,36 @@ struct State {
     is_pressed: bool,
 }
 
-#[typetag::serde]
-impl Widget for Button {
+impl<Message> Widget<Message> for Button<Message> {
     fn size_hint(&self) -> Size<Length> {
         Size::new(self.width, self.height)
     }
 
-    fn data(&self) -> Vec<u8> {
-        bincode::serde::encode_to_vec(self, bincode::config::standard()).unwrap()
+    fn children(&self) -> Vec<&Element<Message>> {
+        vec![&self.content]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut Element<Message>> {
+        vec![&mut self.content]
     }
 }
 
-impl<'a> From<Button> for Element {
-    fn from(value: Button) -> Self {
-        Element::new(Box::new(value))
+impl<'a, Message> From<Button<Message>> for Element<Message> {
+    fn from(value: Button<Message>) -> Self {
+        Element::Button(Box::new(value))
     }
 }
 
-impl<'a> From<Button> for iced::Element<'a, PluginRequest, iced::Theme, iced::Renderer>
+impl<'a> From<&'a Button> for iced::Element<'a, PluginRequest, iced::Theme, iced::Renderer>
 {
-    fn from(value: Button) -> Self {
+    fn from(value: &'a Button) -> Self {
+        let class = value.class.clone();
         iced::widget::Button::new(to_element(&value.content))
-            .on_press_maybe(value.on_press.map(|b| PluginRequest::Message(b)))
+            .on_press_maybe(value.on_press.clone().map(|b| PluginRequest::Message(b)))
             .width(value.width)
             .height(value.height)
             .padding(value.padding)
-            .style(move |theme, status| {
-                if let Some(class) = value.class {
-                    let style = match status {
-                        button::Status::Active => class.active,
-                        button::Status::Hovered => class.hovered,
-                        button::Status::Pressed => class.pressed,
-                        button::Status::Disabled => class.disabled,
-                    };
-                    style.into()
-                } else {
-                    button::primary(&theme, status)
-                }
-            })
+            .style(move |theme, status| class.resolve(theme, status.into()).into())
             .clip(value.clip)
             .into()
     }
@@ -252,6 +246,60 @@ pub enum Status {
     Disabled,
 }
 
+impl From<button::Status> for Status {
+    fn from(value: button::Status) -> Self {
+        match value {
+            button::Status::Active => Status::Active,
+            button::Status::Hovered => Status::Hovered,
+            button::Status::Pressed => Status::Pressed,
+            button::Status::Disabled => Status::Disabled,
+        }
+    }
+}
+
+/// The style class of a [`Button`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum Class {
+    /// See [`primary`].
+    #[default]
+    Primary,
+    /// See [`secondary`].
+    Secondary,
+    /// See [`success`].
+    Success,
+    /// See [`danger`].
+    Danger,
+    /// See [`text`].
+    Text,
+    /// A fixed style, independent of the theme.
+    Style(Box<StateStyle>),
+}
+
+impl Class {
+    /// The [`Style`] of the class in the given theme and status.
+    pub fn resolve(&self, theme: &Theme, status: Status) -> Style {
+        match self {
+            Class::Primary => primary(theme, status),
+            Class::Secondary => secondary(theme, status),
+            Class::Success => success(theme, status),
+            Class::Danger => danger(theme, status),
+            Class::Text => text(theme, status),
+            Class::Style(style) => match status {
+                Status::Active => style.active,
+                Status::Hovered => style.hovered,
+                Status::Pressed => style.pressed,
+                Status::Disabled => style.disabled,
+            },
+        }
+    }
+}
+
+impl From<StateStyle> for Class {
+    fn from(style: StateStyle) -> Self {
+        Class::Style(Box::new(style))
+    }
+}
+
 /// The style of a button.
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct StateStyle {
@@ -434,3 +482,21 @@ fn disabled(style: Style) -> Style {
         ..style
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::widgets::text::Text;
+
+    #[test]
+    fn dropped_messages_leave_the_button_without_a_handler() {
+        let button = Button::new(Text::new("Open")).on_press(1u8);
+
+        let encoded = button.filter_map(&|message: u8| Some(vec![message]));
+        assert_eq!(encoded.on_press, Some(vec![1]));
+
+        let button = Button::new(Text::new("Open")).on_press(1u8);
+        let dropped = button.filter_map(&|_| None::<Vec<u8>>);
+        assert_eq!(dropped.on_press, None);
+    }
+}
@@ -32,7 +32,7 @@ use crate::{
 /// }
 /// ```
 #[derive(Debug, Serialize, Deserialize)]
-pub struct Column {
+pub struct Column<Message = Vec<u8>> {
     spacing: f32,
     #[serde(with = "PaddingDef")]
     padding: Padding,
@@ -44,10 +44,10 @@ pub struct Column {
     #[serde(with = "AlignmentDef")]
     align: Alignment,
     clip: bool,
-    children: Vec<Element>,
+    children: Vec<Element<Message>>,
 }
 
-impl Column {
+impl<Message> Column<Message> {
     /// Creates an empty [`Column`].
     pub fn new() -> Self {
         Self::from_vec(Vec::new())
@@ -59,7 +59,7 @@ impl Column {
     }
 
     /// Creates a [`Column`] with the given elements.
-    pub fn with_children(children: impl IntoIterator<Item = Element>) -> Self {
+    pub fn with_children(children: impl IntoIterator<Item = Element<Message>>) -> Self {
         let iterator = children.into_iter();
 
         Self::with_capacity(iterator.size_hint().0).extend(iterator)
@@ -72,7 +72,7 @@ impl Column {
     ///
     /// If any of the children have a [`Length::Fill`] strategy, you will need to
     /// call [`Column::width`] or [`Column::height`] accordingly.
-    pub fn from_vec(children: Vec<Element>) -> Self {
+    pub fn from_vec(children: Vec<Element<Message>>) -> Self {
         Self {
             spacing: 0.0,
             padding: Padding::ZERO,
@@ -133,7 +133,7 @@ impl Column {
     }
 
     /// Adds an element to the [`Column`].
-    pub fn push(mut self, child: impl Into<Element>) -> Self {
+    pub fn push(mut self, child: impl Into<Element<Message>>) -> Self {
         let child = child.into();
         let child_size = child.as_widget().size_hint();
 
@@ -145,7 +145,7 @@ impl Column {
     }
 
     /// Adds an element to the [`Column`], if `Some`.
-    pub fn push_maybe(self, child: Option<impl Into<Element>>) -> Self {
+    pub fn push_maybe(self, child: Option<impl Into<Element<Message>>>) -> Self {
         if let Some(child) = child {
             self.push(child)
         } else {
@@ -154,43 +154,67 @@ impl Column {
     }
 
     /// Extends the [`Column`] with the given children.
-    pub fn extend(self, children: impl IntoIterator<Item = Element>) -> Self {
+    pub fn extend(self, children: impl IntoIterator<Item = Element<Message>>) -> Self {
         children.into_iter().fold(self, Self::push)
     }
+
+    pub(crate) fn filter_map<B>(self, f: &dyn Fn(Message) -> Option<B>) -> Column<B> {
+        Column {
+            spacing: self.spacing,
+            padding: self.padding,
+            width: self.width,
+            height: self.height,
+            max_width: self.max_width,
+            align: self.align,
+            clip: self.clip,
+            children: self
+                .children
+                .into_iter()
+                .map(|child| child.filter_map(f))
+                .collect(),
+        }
+    }
 }
 
-impl Default for Column {
+impl<Message> Default for Column<Message> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl FromIterator<Element> for Column {
-    fn from_iter<T: IntoIterator<Item = Element>>(iter: T) -> Self {
+impl<Message> FromIterator<Element<Message>> for Column<Message> {
+    fn from_iter<T: IntoIterator<Item = Element<Message>>>(iter: T) -> Self {
         Self::with_children(iter)
     }
 }
 
-#[typetag::serde]
-impl Widget for Column {
+impl<Message> Widget<Message> for Column<Message> {
     fn size_hint(&self) -> Size<Length> {
         Size::new(self.width, self.height)
     }
-    fn data(&self) -> Vec<u8> {
-        bincode::serde::encode_to_vec(self, bincode::config::standard()).unwrap()
+    fn children(&self) -> Vec<&Element<Message>> {
+        self.children.iter().collect()
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut Element<Message>> {
+        self.children.iter_mut().collect()
+    }
+
+    fn child_list(&mut self) -> Option<&mut Vec<Element<Message>>> {
+        Some(&mut self.children)
     }
 }
 
-impl From<Column> for Element {
-    fn from(column: Column) -> Self {
-        Self::new(Box::new(column))
+impl<Message> From<Column<Message>> for Element<Message> {
+    fn from(column: Column<Message>) -> Self {
+        Self::Column(column)
     }
 }
 
-impl<'a> From<Column> for iced::Element<'a, PluginRequest, iced::Theme, iced::Renderer>
+impl<'a> From<&'a Column> for iced::Element<'a, PluginRequest, iced::Theme, iced::Renderer>
 {
-    fn from(value: Column) -> Self {
-        iced::widget::Column::with_children(value.children.iter().map(|c| to_element(c)))
+    fn from(value: &'a Column) -> Self {
+        iced::widget::Column::with_children(value.children.iter().map(to_element))
             .align_x(value.align)
             .clip(value.clip)
             .width(value.width)
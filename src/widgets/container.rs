@@ -41,7 +41,7 @@ use crate::{
 /// ```
 #[allow(missing_debug_implementations)]
 #[derive(Serialize, Deserialize, Debug)]
-pub struct Container {
+pub struct Container<Message = Vec<u8>> {
     max_width: f32,
     max_height: f32,
     id: Option<Id>,
@@ -56,13 +56,13 @@ pub struct Container {
     #[serde(with = "VerticalDef")]
     vertical_alignment: alignment::Vertical,
     clip: bool,
-    content: Element,
-    class: Option<Style>,
+    content: Element<Message>,
+    class: Class,
 }
 
-impl Container {
+impl<Message> Container<Message> {
     /// Creates a [`Container`] with the given content.
-    pub fn new(content: impl Into<Element>) -> Self {
+    pub fn new(content: impl Into<Element<Message>>) -> Self {
         let content = content.into();
         let size = content.as_widget().size_hint();
 
@@ -76,7 +76,7 @@ impl Container {
             horizontal_alignment: alignment::Horizontal::Left,
             vertical_alignment: alignment::Vertical::Top,
             clip: false,
-            class: None,
+            class: Class::default(),
             content,
         }
     }
@@ -179,42 +179,58 @@ impl Container {
     }
 
     /// Sets the class of the [`Container`].
-    pub fn class(mut self, class: Style) -> Self {
-        self.class = Some(class);
+    ///
+    /// Classes other than [`Class::Style`] are resolved against the theme of
+    /// the host.
+    pub fn class(mut self, class: impl Into<Class>) -> Self {
+        self.class = class.into();
         self
     }
 
     /// Sets the style of the [`Container`].
     #[must_use]
     pub fn style(mut self, style: Style) -> Self {
-        self.class = Some(style);
+        self.class = Class::Style(Box::new(style));
         self
     }
+
+    pub(crate) fn filter_map<B>(self, f: &dyn Fn(Message) -> Option<B>) -> Container<B> {
+        Container {
+            max_width: self.max_width,
+            max_height: self.max_height,
+            id: self.id,
+            padding: self.padding,
+            width: self.width,
+            height: self.height,
+            horizontal_alignment: self.horizontal_alignment,
+            vertical_alignment: self.vertical_alignment,
+            clip: self.clip,
+            content: self.content.filter_map(f),
+            class: self.class,
+        }
+    }
 }
 
-impl From<Container> for Element {
-    fn from(value: Container) -> Self {
-        Element::new(Box::new(value))
+impl<Message> From<Container<Message>> for Element<Message> {
+    fn from(value: Container<Message>) -> Self {
+        Element::Container(Box::new(value))
     }
 }
 
-impl<'a> From<Container> for iced::Element<'a, PluginRequest, iced::Theme, iced::Renderer>
+impl<'a> From<&'a Container> for iced::Element<'a, PluginRequest, iced::Theme, iced::Renderer>
 {
-    fn from(value: Container) -> Self {
+    fn from(value: &'a Container) -> Self {
+        let class = value.class.clone();
         let mut container= container(to_element(&value.content))
             .width(value.width)
             .height(value.height)
-            .style(move |theme| if let Some(class) = value.class {
-                class.into()
-            } else {
-                iced::widget::container::transparent(theme)
-            })
+            .style(move |theme| class.resolve(theme).into())
             .align_x(value.horizontal_alignment)
             .align_y(value.vertical_alignment)
             .clip(value.clip)
             .padding(value.padding);
 
-        if let Some(id) = value.id {
+        if let Some(id) = value.id.clone() {
             container = container.id(id.into())
         }
 
@@ -222,19 +238,57 @@ impl<'a> From<Container> for iced::Element<'a, PluginRequest, iced::Theme, iced:
     }
 }
 
-#[typetag::serde]
-impl Widget for Container {
+impl<Message> Widget<Message> for Container<Message> {
     fn size_hint(&self) -> Size<Length> {
         self.content.as_widget().size_hint()
     }
 
-    fn data(&self) -> Vec<u8> {
-        bincode::serde::encode_to_vec(self, bincode::config::standard()).unwrap()
+    fn children(&self) -> Vec<&Element<Message>> {
+        vec![&self.content]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut Element<Message>> {
+        vec![&mut self.content]
+    }
+}
+
+/// The style class of a [`Container`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum Class {
+    /// See [`transparent`].
+    #[default]
+    Transparent,
+    /// See [`rounded_box`].
+    RoundedBox,
+    /// See [`bordered_box`].
+    BorderedBox,
+    /// See [`dark`].
+    Dark,
+    /// A fixed style, independent of the theme.
+    Style(Box<Style>),
+}
+
+impl Class {
+    /// The [`Style`] of the class in the given theme.
+    pub fn resolve(&self, theme: &Theme) -> Style {
+        match self {
+            Class::Transparent => transparent(theme),
+            Class::RoundedBox => rounded_box(theme),
+            Class::BorderedBox => bordered_box(theme),
+            Class::Dark => dark(theme),
+            Class::Style(style) => **style,
+        }
+    }
+}
+
+impl From<Style> for Class {
+    fn from(style: Style) -> Self {
+        Class::Style(Box::new(style))
     }
 }
 
 /// The appearance of a container.
-#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
 pub struct Style {
     /// The text [`Color`] of the container.
     #[serde(with = "opt_color")]
@@ -0,0 +1,125 @@
+//! Widgets that are not built into the crate.
+//!
+//! A custom widget is a serializable type implementing [`CustomWidget`],
+//! usually defined in a crate shared by a host and its plugins. Plugins put
+//! them into their views with [`custom`] or [`custom_or`], and hosts build
+//! them with the view function passed to
+//! [`registry::register`](crate::widgets::registry::register).
+use std::{
+    any::Any,
+    mem,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use iced::{Length, Size};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::{
+    error::Error,
+    widgets::{element::Element, registry, space::Space, widget::Widget},
+};
+
+/// A widget defined outside of this crate.
+pub trait CustomWidget: Serialize + DeserializeOwned + Send + Sync + 'static {
+    /// The name the widget is registered as.
+    ///
+    /// Names are shared by all crates, so they should be namespaced, e.g.
+    /// `"sway.workspaces"`.
+    const KIND: &'static str;
+}
+
+/// A widget of a kind registered with the host, see
+/// [`registry`](crate::widgets::registry).
+///
+/// The payload is opaque to the connection; it is only decoded by the host
+/// when the widget is first rendered.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Custom {
+    /// The registered name of the widget.
+    pub kind: String,
+    /// The encoded state of the widget.
+    pub payload: Vec<u8>,
+    #[serde(skip)]
+    pub(crate) state: OnceLock<Result<Arc<dyn Any + Send + Sync>, Error>>,
+}
+
+impl Custom {
+    /// Creates a [`Custom`] widget of the given kind from an encoded payload.
+    pub fn new(kind: impl Into<String>, payload: Vec<u8>) -> Self {
+        Custom {
+            kind: kind.into(),
+            payload,
+            state: OnceLock::new(),
+        }
+    }
+
+    /// Creates a [`Custom`] widget of the given kind, encoding its state.
+    pub fn encode<T: Serialize>(
+        kind: impl Into<String>,
+        state: &T,
+    ) -> Result<Self, bincode::error::EncodeError> {
+        let payload = bincode::serde::encode_to_vec(state, bincode::config::standard())?;
+        Ok(Self::new(kind, payload))
+    }
+}
+
+impl<Message> Widget<Message> for Custom {
+    fn size_hint(&self) -> Size<Length> {
+        Size::new(Length::Shrink, Length::Shrink)
+    }
+}
+
+impl<Message> From<Custom> for Element<Message> {
+    fn from(value: Custom) -> Self {
+        Element::Custom(value)
+    }
+}
+
+/// The errors of the custom widgets that could not be encoded since they
+/// were last taken.
+static ERRORS: Mutex<Vec<Error>> = Mutex::new(Vec::new());
+
+/// Creates an [`Element`] from a custom widget.
+///
+/// If the widget cannot be encoded, it is left out of the view and the error
+/// is passed to [`Application::on_error`](crate::Application::on_error).
+pub fn custom<Message, W: CustomWidget>(widget: &W) -> Element<Message> {
+    match Custom::encode(W::KIND, widget) {
+        Ok(custom) => custom.into(),
+        Err(error) => {
+            ERRORS
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(error.into());
+            Space::new(Length::Shrink, Length::Shrink).into()
+        }
+    }
+}
+
+/// Takes the errors of the custom widgets that could not be encoded.
+pub(crate) fn take_errors() -> Vec<Error> {
+    mem::take(&mut *ERRORS.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+/// Creates an [`Element`] from a custom widget, or uses `fallback` if the
+/// host did not announce support for it during the handshake.
+///
+/// Plugins must list the kind in
+/// [`Application::widgets`](crate::Application::widgets) for the host to
+/// announce it.
+pub fn custom_or<Message, W: CustomWidget>(
+    widget: &W,
+    fallback: impl Into<Element<Message>>,
+) -> Element<Message> {
+    if registry::host_supports(W::KIND) {
+        custom(widget)
+    } else {
+        fallback.into()
+    }
+}
+
+/// Creates an [`Element`] from a custom widget, or empty [`Space`] if the
+/// host does not support it.
+pub fn custom_or_empty<Message, W: CustomWidget>(widget: &W) -> Element<Message> {
+    custom_or(widget, Space::new(Length::Shrink, Length::Shrink))
+}
@@ -1,58 +1,133 @@
 use serde::{Deserialize, Serialize};
 
-use crate::{widgets::{button::Button, column::Column, container::Container, space::Space, stack::Stack, text::Text, widget::Widget}, PluginRequest};
+use crate::{error::Error, widgets::{button::Button, column::Column, container::Container, custom::Custom, icon::Icon, placeholder::placeholder, registry, space::Space, stack::Stack, text::Text, widget::Widget}, PluginRequest};
 
+/// A tree of widgets, as sent from a plugin to the host.
+///
+/// Plugins build elements producing their own `Message` type, which are
+/// turned into elements producing encoded messages with [`Element::map`]
+/// before they are sent.
+///
+/// Variants are encoded by their index, so new widgets must only ever be
+/// appended to keep older plugins compatible. Widgets outside of this crate
+/// are sent as [`Custom`] elements and resolved through the [`registry`].
 #[derive(Serialize, Deserialize, Debug)]
-pub struct Element {
-    widget: Box<dyn Widget>,
+pub enum Element<Message = Vec<u8>> {
+    Container(Box<Container<Message>>),
+    Space(Space),
+    Stack(Stack<Message>),
+    Button(Box<Button<Message>>),
+    Text(Box<Text>),
+    Column(Column<Message>),
+    Custom(Custom),
+    Icon(Icon),
 }
 
-impl Element {
-    pub fn new(widget: Box<dyn Widget>) -> Self {
-        Element { widget }
+impl<Message> Element<Message> {
+    pub fn as_widget(&self) -> &dyn Widget<Message> {
+        match self {
+            Element::Container(widget) => widget.as_ref(),
+            Element::Space(widget) => widget,
+            Element::Stack(widget) => widget,
+            Element::Button(widget) => widget.as_ref(),
+            Element::Text(widget) => widget.as_ref(),
+            Element::Column(widget) => widget,
+            Element::Custom(widget) => widget,
+            Element::Icon(widget) => widget,
+        }
+    }
+
+    pub fn as_widget_mut(&mut self) -> &mut dyn Widget<Message> {
+        match self {
+            Element::Container(widget) => widget.as_mut(),
+            Element::Space(widget) => widget,
+            Element::Stack(widget) => widget,
+            Element::Button(widget) => widget.as_mut(),
+            Element::Text(widget) => widget.as_mut(),
+            Element::Column(widget) => widget,
+            Element::Custom(widget) => widget,
+            Element::Icon(widget) => widget,
+        }
+    }
+
+    /// The name of the kind of widget at the root of the tree.
+    pub fn kind(&self) -> &str {
+        match self {
+            Element::Container(_) => "Container",
+            Element::Space(_) => "Space",
+            Element::Stack(_) => "Stack",
+            Element::Button(_) => "Button",
+            Element::Text(_) => "Text",
+            Element::Column(_) => "Column",
+            Element::Custom(custom) => &custom.kind,
+            Element::Icon(_) => "Icon",
+        }
+    }
+
+    /// Applies a transformation to the messages produced by the element.
+    ///
+    /// This is useful to compose views of sub-components producing their
+    /// own messages.
+    pub fn map<B>(self, f: impl Fn(Message) -> B) -> Element<B> {
+        self.filter_map(&|message| Some(f(message)))
     }
-    pub fn as_widget(&self) -> &dyn Widget {
-        self.widget.as_ref()
+
+    /// Applies a transformation to the messages produced by the element,
+    /// dropping the handlers of the messages it returns `None` for.
+    pub(crate) fn filter_map<B>(self, f: &dyn Fn(Message) -> Option<B>) -> Element<B> {
+        match self {
+            Element::Container(widget) => Element::Container(Box::new(widget.filter_map(f))),
+            Element::Space(widget) => Element::Space(widget),
+            Element::Stack(widget) => Element::Stack(widget.filter_map(f)),
+            Element::Button(widget) => Element::Button(Box::new(widget.filter_map(f))),
+            Element::Text(widget) => Element::Text(widget),
+            Element::Column(widget) => Element::Column(widget.filter_map(f)),
+            Element::Custom(widget) => Element::Custom(widget),
+            Element::Icon(widget) => Element::Icon(widget),
+        }
     }
 }
 
-pub fn to_element<'a>(
+/// Converts an [`Element`] into an iced element.
+///
+/// The element borrows from the tree, which the host keeps in the form it was
+/// decoded from the connection, so no built-in widget is serialized again to
+/// render it. Subtrees that cannot be converted are rendered as a
+/// [`placeholder`], so a single broken widget does not take the rest of the
+/// view with it.
+pub fn to_element(
     element: &Element,
-) -> iced::Element<'a, PluginRequest, iced::Theme, iced::Renderer>
+) -> iced::Element<'_, PluginRequest, iced::Theme, iced::Renderer>
 {
-    let slice = element.as_widget().data();
-    let type_name = &element.as_widget().typetag_name().to_string();
-    match type_name.as_str() {
-        "Container" => {
-            let (state, _): (Container, _) =
-                bincode::serde::decode_from_slice(&slice, bincode::config::standard()).unwrap();
-            state.into()
-        }
-        "Space" => {
-            let (state, _): (Space, _) =
-                bincode::serde::decode_from_slice(&slice, bincode::config::standard()).unwrap();
-            state.into()
+    match element {
+        Element::Container(widget) => widget.as_ref().into(),
+        Element::Space(widget) => widget.into(),
+        Element::Stack(widget) => widget.into(),
+        Element::Button(widget) => widget.as_ref().into(),
+        Element::Text(widget) => widget.as_ref().into(),
+        Element::Column(widget) => widget.into(),
+        Element::Custom(custom) => {
+            registry::construct(custom).unwrap_or_else(|error| placeholder(&error))
         }
-        "Stack" => {
-            let (state, _): (Stack, _) =
-                bincode::serde::decode_from_slice(&slice, bincode::config::standard()).unwrap();
-            state.into()
-        }
-        "Button" => {
-            let (state, _): (Button, _) =
-                bincode::serde::decode_from_slice(&slice, bincode::config::standard()).unwrap();
-            state.into()
-        }
-        "Text" => {
-            let (state, _): (Text, _) =
-                bincode::serde::decode_from_slice(&slice, bincode::config::standard()).unwrap();
-            state.into()
-        }
-        "Column" => {
-            let (state, _): (Column, _) =
-                bincode::serde::decode_from_slice(&slice, bincode::config::standard()).unwrap();
-            state.into()
-        }
-        _ => todo!(),
+        Element::Icon(widget) => widget.into(),
+    }
+}
+
+/// Returns the errors [`to_element`] would render placeholders for.
+pub fn validate(element: &Element) -> Vec<Error> {
+    let mut errors = Vec::new();
+    collect_errors(element, &mut errors);
+    errors
+}
+
+fn collect_errors(element: &Element, errors: &mut Vec<Error>) {
+    if let Element::Custom(custom) = element
+        && let Err(error) = registry::construct(custom)
+    {
+        errors.push(error);
+    }
+
+    for child in element.as_widget().children() {
+        collect_errors(child, errors);
     }
 }
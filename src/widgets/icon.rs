@@ -0,0 +1,86 @@
+//! Icons of the icon theme of the host.
+use std::path::PathBuf;
+
+use iced::{
+    Length, Size,
+    widget::{Space, image, svg},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{Element, PluginRequest, icons, widgets::widget::Widget};
+
+/// A freedesktop icon, looked up by name in the icon theme of the host.
+///
+/// Icons that cannot be found leave empty space of their size, see
+/// [`icons::lookup`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Icon {
+    /// Where the image of the icon comes from.
+    pub source: Source,
+    /// The size of the icon in logical pixels.
+    pub size: u16,
+}
+
+impl Icon {
+    /// Creates a new [`Icon`] with the given name and size.
+    pub fn new(name: impl Into<String>, size: u16) -> Self {
+        Icon {
+            source: Source::Name(name.into()),
+            size,
+        }
+    }
+
+    /// Creates a new [`Icon`] showing the image file at the given absolute
+    /// path, see [`icons::lookup_file`].
+    pub fn from_file(path: impl Into<PathBuf>, size: u16) -> Self {
+        Icon {
+            source: Source::File(path.into()),
+            size,
+        }
+    }
+}
+
+/// Where the image of an [`Icon`] comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Source {
+    /// An icon name, looked up in the icon theme of the host.
+    Name(String),
+    /// An absolute path to a PNG or SVG file.
+    File(PathBuf),
+}
+
+impl<Message> Widget<Message> for Icon {
+    fn size_hint(&self) -> Size<Length> {
+        let size = Length::Fixed(f32::from(self.size));
+        Size::new(size, size)
+    }
+}
+
+impl<Message> From<Icon> for Element<Message> {
+    fn from(value: Icon) -> Self {
+        Element::Icon(value)
+    }
+}
+
+impl<'a> From<&'a Icon> for iced::Element<'a, PluginRequest, iced::Theme, iced::Renderer> {
+    fn from(value: &'a Icon) -> Self {
+        let size = Length::Fixed(f32::from(value.size));
+        let path = match &value.source {
+            Source::Name(name) => icons::lookup(name, value.size, icons::scale()),
+            Source::File(path) => icons::lookup_file(path),
+        };
+        match path {
+            Some(path) if path.extension().is_some_and(|ext| ext == "svg") => {
+                svg(svg::Handle::from_path(path))
+                    .width(size)
+                    .height(size)
+                    .into()
+            }
+            Some(path) => image(image::Handle::from_path(path))
+                .width(size)
+                .height(size)
+                .into(),
+            None => Space::new(size, size).into(),
+        }
+    }
+}
@@ -1,5 +1,10 @@
 pub mod container;
+pub mod custom;
 pub mod element;
+pub mod icon;
+pub mod placeholder;
+pub mod popup;
+pub mod registry;
 pub mod serde_types;
 pub mod space;
 pub mod stack;
@@ -0,0 +1,69 @@
+//! Fallbacks rendered in place of views that cannot be displayed.
+use iced::{
+    Alignment, Background, Border, Length, Theme,
+    widget::{center, container, opaque, row, stack, text, tooltip},
+};
+
+use crate::error::Error;
+
+/// Creates an element standing in for a widget that failed to convert.
+///
+/// The element shows a warning sign and a short message, with the full
+/// error available in a tooltip.
+pub fn placeholder<'a, Message: 'a>(
+    error: &Error,
+) -> iced::Element<'a, Message, iced::Theme, iced::Renderer> {
+    let content = container(
+        row![text("\u{26a0}"), text("Widget error").size(12)]
+            .spacing(4)
+            .align_y(Alignment::Center),
+    )
+    .padding([2, 6])
+    .style(style);
+
+    let details = container(text(error.to_string()).size(12))
+        .padding(6)
+        .style(container::rounded_box);
+
+    tooltip(content, details, tooltip::Position::Bottom).into()
+}
+
+/// Greys out the view of a plugin that is not responding.
+///
+/// The view is covered by an hourglass, which also keeps it from receiving
+/// input until the plugin responds again.
+pub fn unresponsive<'a, Message: 'a>(
+    content: iced::Element<'a, Message, iced::Theme, iced::Renderer>,
+) -> iced::Element<'a, Message, iced::Theme, iced::Renderer> {
+    let overlay = center(text("\u{231b}").size(12))
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .style(dimmed);
+
+    stack![content, opaque(overlay)].into()
+}
+
+fn dimmed(theme: &Theme) -> container::Style {
+    let palette = theme.extended_palette();
+
+    container::Style {
+        text_color: Some(palette.background.base.text),
+        background: Some(Background::Color(palette.background.base.color.scale_alpha(0.7))),
+        ..container::Style::default()
+    }
+}
+
+fn style(theme: &Theme) -> container::Style {
+    let palette = theme.extended_palette();
+
+    container::Style {
+        text_color: Some(palette.danger.base.color),
+        background: Some(Background::Color(palette.danger.weak.color.scale_alpha(0.2))),
+        border: Border {
+            color: palette.danger.base.color,
+            width: 1.0,
+            radius: 2.0.into(),
+        },
+        ..container::Style::default()
+    }
+}
@@ -0,0 +1,74 @@
+//! Popup surfaces opened by plugins, such as calendars or mixers.
+//!
+//! Plugins return their open popups from
+//! [`Application::popups`](crate::Application::popups). Every popup is
+//! anchored to a [`Container`](crate::widgets::container::Container) of the
+//! view of the plugin by its [`Id`], and hosts show it next to that widget,
+//! either as a popup surface of their own or inside their window with
+//! [`overlay`].
+//!
+//! Hosts may dismiss popups at any time, e.g. when escape is pressed, which
+//! plugins learn through [`Event::PopupDismissed`](crate::event::Event).
+use iced::{
+    Length, Rectangle, Task,
+    widget::{Space, column, container::visible_bounds, row, stack},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{Element, widgets::container::Id};
+
+/// A named popup, anchored to a widget of the plugin that opened it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Popup<Message = Vec<u8>> {
+    /// The name of the popup, unique among the popups of a plugin.
+    pub name: String,
+    /// The [`Id`] of the container the popup is anchored to.
+    pub anchor: Id,
+    /// The contents of the popup.
+    pub view: Element<Message>,
+}
+
+impl<Message> Popup<Message> {
+    /// Creates a new [`Popup`] anchored to the container with the given
+    /// [`Id`].
+    pub fn new(name: impl Into<String>, anchor: Id, view: impl Into<Element<Message>>) -> Self {
+        Popup {
+            name: name.into(),
+            anchor,
+            view: view.into(),
+        }
+    }
+
+    /// Returns the bounds of the widget the popup is anchored to, if it is
+    /// visible.
+    pub fn anchor_bounds(&self) -> Task<Option<Rectangle>> {
+        visible_bounds(self.anchor.clone().into())
+    }
+
+    pub(crate) fn filter_map<B>(self, f: &dyn Fn(Message) -> Option<B>) -> Popup<B> {
+        Popup {
+            name: self.name,
+            anchor: self.anchor,
+            view: self.view.filter_map(f),
+        }
+    }
+}
+
+/// Shows a popup below the given anchor bounds, on top of `base`.
+///
+/// The popup is clipped to the bounds of `base`, so hosts showing popups
+/// beyond their window need a popup surface instead.
+pub fn overlay<'a, Message: 'a>(
+    base: impl Into<iced::Element<'a, Message, iced::Theme, iced::Renderer>>,
+    popup: impl Into<iced::Element<'a, Message, iced::Theme, iced::Renderer>>,
+    anchor: Rectangle,
+) -> iced::Element<'a, Message, iced::Theme, iced::Renderer> {
+    stack![
+        base.into(),
+        column![
+            Space::with_height(Length::Fixed(anchor.y + anchor.height)),
+            row![Space::with_width(Length::Fixed(anchor.x)), popup.into()],
+        ],
+    ]
+    .into()
+}
@@ -0,0 +1,170 @@
+//! The custom widgets a host knows how to build.
+//!
+//! Hosts register every [`CustomWidget`] they support before plugins connect:
+//!
+//! ```no_run
+//! # use azalea_applet::{PluginRequest, iced, serde::{Deserialize, Serialize}};
+//! # use azalea_applet::widgets::{custom::CustomWidget, registry};
+//! #[derive(Serialize, Deserialize)]
+//! struct Workspaces {
+//!     active: usize,
+//!     count: usize,
+//! }
+//!
+//! impl CustomWidget for Workspaces {
+//!     const KIND: &'static str = "example.workspaces";
+//! }
+//!
+//! fn view(workspaces: &Workspaces) -> iced::Element<'_, PluginRequest> {
+//!     iced::widget::text!("{}/{}", workspaces.active, workspaces.count).into()
+//! }
+//!
+//! registry::register::<Workspaces>(view);
+//! ```
+//!
+//! Plugins announce the kinds they use during the handshake and are told
+//! which of them the host supports, see [`host_supports`]. Custom elements
+//! of unregistered kinds are rendered as a
+//! [`placeholder`](crate::widgets::placeholder::placeholder).
+use std::{
+    any::Any,
+    collections::{HashMap, HashSet},
+    sync::{Arc, LazyLock, RwLock},
+};
+
+use crate::{
+    PluginRequest,
+    error::Error,
+    widgets::custom::{Custom, CustomWidget},
+};
+
+type State = Arc<dyn Any + Send + Sync>;
+
+type View = dyn for<'a> Fn(&'a (dyn Any + Send + Sync)) -> Option<iced::Element<'a, PluginRequest, iced::Theme, iced::Renderer>>
+    + Send
+    + Sync;
+
+#[derive(Clone)]
+struct Entry {
+    decode: fn(&[u8]) -> Result<State, Error>,
+    view: Arc<View>,
+}
+
+/// The widgets registered on the host.
+static REGISTERED: LazyLock<RwLock<HashMap<String, Entry>>> = LazyLock::new(Default::default);
+
+/// The widgets the host announced to this plugin.
+static SUPPORTED: LazyLock<RwLock<HashSet<String>>> = LazyLock::new(Default::default);
+
+/// Registers a custom widget with the host, built by the given view function.
+///
+/// A widget registered earlier under the same [`CustomWidget::KIND`] is
+/// replaced.
+pub fn register<W: CustomWidget>(
+    view: for<'a> fn(&'a W) -> iced::Element<'a, PluginRequest, iced::Theme, iced::Renderer>,
+) {
+    let entry = Entry {
+        decode: decode_state::<W>,
+        // States decoded before the kind was registered again may be of
+        // another type.
+        view: Arc::new(move |state| state.downcast_ref().map(view)),
+    };
+
+    REGISTERED
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(W::KIND.to_string(), entry);
+}
+
+/// Returns whether a custom widget of the given kind is registered.
+pub fn is_registered(kind: &str) -> bool {
+    REGISTERED
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .contains_key(kind)
+}
+
+/// Returns whether the host announced support for the given kind.
+///
+/// Always `false` before the handshake with the host completed.
+pub fn host_supports(kind: &str) -> bool {
+    SUPPORTED
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .contains(kind)
+}
+
+/// Splits the kinds a plugin announced into those the host supports and
+/// those it does not.
+pub(crate) fn negotiate(kinds: &[String]) -> (Vec<String>, Vec<String>) {
+    kinds.iter().cloned().partition(|kind| is_registered(kind))
+}
+
+pub(crate) fn set_supported(kinds: Vec<String>) {
+    *SUPPORTED.write().unwrap_or_else(|e| e.into_inner()) = kinds.into_iter().collect();
+}
+
+/// Builds a custom element, decoding its payload on first use.
+pub(crate) fn construct(
+    custom: &Custom,
+) -> Result<iced::Element<'_, PluginRequest, iced::Theme, iced::Renderer>, Error> {
+    let entry = REGISTERED
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&custom.kind)
+        .cloned()
+        .ok_or_else(|| Error::UnknownWidget(custom.kind.clone()))?;
+
+    let state = custom
+        .state
+        .get_or_init(|| (entry.decode)(&custom.payload))
+        .as_ref()
+        .map_err(Clone::clone)?;
+
+    (entry.view)(state.as_ref()).ok_or_else(|| {
+        Error::Codec(format!(
+            "`{}` was registered again with another type",
+            custom.kind
+        ))
+    })
+}
+
+fn decode_state<W: CustomWidget>(payload: &[u8]) -> Result<State, Error> {
+    let (state, _): (W, _) =
+        bincode::serde::decode_from_slice(payload, bincode::config::standard())?;
+    Ok(Arc::new(state))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct Before(u32);
+
+    impl CustomWidget for Before {
+        const KIND: &'static str = "test.registered-again";
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct After(u32);
+
+    impl CustomWidget for After {
+        const KIND: &'static str = "test.registered-again";
+    }
+
+    #[test]
+    fn registering_again_with_another_type_does_not_panic() {
+        register::<Before>(|_| iced::widget::Space::new(0, 0).into());
+        let custom = Custom::encode(Before::KIND, &Before(1)).unwrap();
+        assert!(construct(&custom).is_ok());
+
+        register::<After>(|_| iced::widget::Space::new(0, 0).into());
+        assert!(matches!(construct(&custom), Err(Error::Codec(_))));
+
+        let custom = Custom::encode(After::KIND, &After(1)).unwrap();
+        assert!(construct(&custom).is_ok());
+    }
+}
@@ -1,4 +1,8 @@
-use std::borrow::Cow;
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    sync::{LazyLock, RwLock},
+};
 
 use iced::{
     widget::text::{LineHeight, Shaping, Wrapping},
@@ -360,7 +364,7 @@ pub enum Family {
 impl From<Family> for iced::font::Family {
     fn from(value: Family) -> Self {
         match value {
-            Family::Name(name) => iced::font::Family::Name(Box::leak(name.into_boxed_str())),
+            Family::Name(name) => iced::font::Family::Name(intern(name)),
             Family::Serif => iced::font::Family::Serif,
             Family::SansSerif => iced::font::Family::SansSerif,
             Family::Cursive => iced::font::Family::Cursive,
@@ -368,7 +372,46 @@ impl From<Family> for iced::font::Family {
             Family::Monospace => iced::font::Family::Monospace,
         }
     }
-} 
+}
+
+impl From<iced::font::Family> for Family {
+    fn from(value: iced::font::Family) -> Self {
+        match value {
+            iced::font::Family::Name(name) => Family::Name(name.to_string()),
+            iced::font::Family::Serif => Family::Serif,
+            iced::font::Family::SansSerif => Family::SansSerif,
+            iced::font::Family::Cursive => Family::Cursive,
+            iced::font::Family::Fantasy => Family::Fantasy,
+            iced::font::Family::Monospace => Family::Monospace,
+        }
+    }
+}
+
+/// Returns a `'static` copy of a family name.
+///
+/// iced only takes `'static` names, and views are converted on every
+/// render, so every name is only leaked once.
+fn intern(name: String) -> &'static str {
+    static NAMES: LazyLock<RwLock<HashSet<&'static str>>> = LazyLock::new(Default::default);
+
+    if let Some(name) = NAMES
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(name.as_str())
+    {
+        return name;
+    }
+
+    let mut names = NAMES.write().unwrap_or_else(|e| e.into_inner());
+    match names.get(name.as_str()) {
+        Some(name) => name,
+        None => {
+            let name: &'static str = Box::leak(name.into_boxed_str());
+            names.insert(name);
+            name
+        }
+    }
+}
 
 /// The weight of some text.
 #[allow(missing_docs)]
@@ -428,6 +471,36 @@ pub struct Font {
     pub style: Style,
 }
 
+impl Font {
+    /// Creates a regular [`Font`] of the family with the given name.
+    ///
+    /// The family can be installed on the host or uploaded by the plugin,
+    /// see [`Application::fonts`](crate::Application::fonts).
+    pub fn with_name(name: impl Into<String>) -> Self {
+        Font {
+            family: Family::Name(name.into()),
+            ..Font::default()
+        }
+    }
+}
+
+impl Default for Font {
+    fn default() -> Self {
+        iced::Font::default().into()
+    }
+}
+
+impl From<iced::Font> for Font {
+    fn from(value: iced::Font) -> Self {
+        Font {
+            family: value.family.into(),
+            weight: value.weight,
+            stretch: value.stretch,
+            style: value.style,
+        }
+    }
+}
+
 impl From<Font> for iced::Font {
     fn from(value: Font) -> Self {
         iced::Font {
@@ -543,20 +616,81 @@ pub enum AlignmentDef {
     End,
 }
 
-/// A [`Theme`] with a customized [`Palette`].
+/// A theme as sent from the host to its plugins.
+///
+/// Besides the palettes of an iced theme, this carries colors iced has no
+/// place for. Plugins can use the style functions of iced with the theme
+/// returned by [`Theme::to_iced`].
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Theme {
+    /// The name of the theme, e.g. `"Dark"` for the built-in dark theme.
+    pub name: String,
     #[serde(with = "PaletteDef")]
     pub palette: Palette,
     #[serde(with = "ExtendedDef")]
     pub extended: palette::Extended,
+    /// The color used to highlight important parts of a view, like the
+    /// active workspace.
+    #[serde(with = "PairDef")]
+    pub accent: Pair,
+    /// The colors of surfaces raised above the background, like popups.
+    ///
+    /// Defaults to the background colors of the iced theme.
+    #[serde(with = "ThemeBackgroundDef")]
+    pub surface: palette::Background,
+}
+
+impl Theme {
+    /// Whether the theme is dark.
+    pub fn is_dark(&self) -> bool {
+        self.extended.is_dark
+    }
+
+    /// Sets the accent color of the [`Theme`].
+    pub fn with_accent(self, accent: Pair) -> Self {
+        Self { accent, ..self }
+    }
+
+    /// Sets the surface colors of the [`Theme`].
+    pub fn with_surface(self, surface: palette::Background) -> Self {
+        Self { surface, ..self }
+    }
+
+    /// Returns the iced theme with the same name and palettes.
+    ///
+    /// Built-in themes are returned as such, others as custom themes.
+    pub fn to_iced(&self) -> iced::Theme {
+        iced::Theme::ALL
+            .iter()
+            .find(|theme| {
+                theme.to_string() == self.name
+                    && theme.palette() == self.palette
+                    && *theme.extended_palette() == self.extended
+            })
+            .cloned()
+            .unwrap_or_else(|| {
+                let extended = self.extended;
+                iced::Theme::custom_with_fn(self.name.clone(), self.palette, move |_| extended)
+            })
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        iced::Theme::default().into()
+    }
 }
 
 impl From<iced::Theme> for Theme {
     fn from(value: iced::Theme) -> Self {
+        let extended = *value.extended_palette();
+
         Self {
+            name: value.to_string(),
             palette: value.palette(),
-            extended: value.extended_palette().clone()
+            accent: extended.primary.base,
+            surface: extended.background,
+            extended,
         }
     }
 }
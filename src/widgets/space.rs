@@ -53,27 +53,22 @@ impl Space {
     }
 }
 
-#[typetag::serde]
-impl Widget for Space {
+impl<Message> Widget<Message> for Space {
     fn size_hint(&self) -> Size<Length> {
         Size::new(self.width, self.height)
     }
-
-    fn data(&self) -> Vec<u8> {
-        bincode::serde::encode_to_vec(self, bincode::config::standard()).unwrap()
-    }
 }
 
-impl From<Space> for Element {
+impl<Message> From<Space> for Element<Message> {
     fn from(value: Space) -> Self {
-        Element::new(Box::new(value))
+        Element::Space(value)
     }
 }
 
-impl<'a, Renderer> From<Space> for iced::Element<'a, PluginRequest, iced::Theme, Renderer>
+impl<'a, Renderer> From<&'a Space> for iced::Element<'a, PluginRequest, iced::Theme, Renderer>
     where Renderer: iced_core::Renderer + 'a
 {
-    fn from(value: Space) -> Self {
+    fn from(value: &'a Space) -> Self {
         iced::widget::Space::new(value.width, value.height).into()
     }
 }
@@ -12,15 +12,15 @@ use crate::{widgets::{element::{to_element, Element}, serde_types::*, widget::Wi
 /// Keep in mind that too much layering will normally produce bad UX as well as
 /// introduce certain rendering overhead. Use this widget sparingly!
 #[derive(Serialize, Deserialize, Debug)]
-pub struct Stack {
+pub struct Stack<Message = Vec<u8>> {
     #[serde(with = "LengthDef")]
     pub width: Length,
     #[serde(with = "LengthDef")]
     pub height: Length,
-    pub children: Vec<Element>,
+    pub children: Vec<Element<Message>>,
 }
 
-impl Stack {
+impl<Message> Stack<Message> {
     /// Creates an empty [`Stack`].
     pub fn new() -> Self {
         Self::from_vec(Vec::new())
@@ -32,7 +32,7 @@ impl Stack {
     }
 
     /// Creates a [`Stack`] with the given elements.
-    pub fn with_children(children: impl IntoIterator<Item = Element>) -> Self {
+    pub fn with_children(children: impl IntoIterator<Item = Element<Message>>) -> Self {
         let iterator = children.into_iter();
 
         Self::with_capacity(iterator.size_hint().0).extend(iterator)
@@ -45,7 +45,7 @@ impl Stack {
     ///
     /// If any of the children have a [`Length::Fill`] strategy, you will need to
     /// call [`Stack::width`] or [`Stack::height`] accordingly.
-    pub fn from_vec(children: Vec<Element>) -> Self {
+    pub fn from_vec(children: Vec<Element<Message>>) -> Self {
         Self {
             width: Length::Shrink,
             height: Length::Shrink,
@@ -66,7 +66,7 @@ impl Stack {
     }
 
     /// Adds an element to the [`Stack`].
-    pub fn push(mut self, child: impl Into<Element>) -> Self {
+    pub fn push(mut self, child: impl Into<Element<Message>>) -> Self {
         let child = child.into();
 
         if self.children.is_empty() {
@@ -81,7 +81,7 @@ impl Stack {
     }
 
     /// Adds an element to the [`Stack`], if `Some`.
-    pub fn push_maybe(self, child: Option<impl Into<Element>>) -> Self {
+    pub fn push_maybe(self, child: Option<impl Into<Element<Message>>>) -> Self {
         if let Some(child) = child {
             self.push(child)
         } else {
@@ -90,39 +90,58 @@ impl Stack {
     }
 
     /// Extends the [`Stack`] with the given children.
-    pub fn extend(self, children: impl IntoIterator<Item = Element>) -> Self {
+    pub fn extend(self, children: impl IntoIterator<Item = Element<Message>>) -> Self {
         children.into_iter().fold(self, Self::push)
     }
+
+    pub(crate) fn filter_map<B>(self, f: &dyn Fn(Message) -> Option<B>) -> Stack<B> {
+        Stack {
+            width: self.width,
+            height: self.height,
+            children: self
+                .children
+                .into_iter()
+                .map(|child| child.filter_map(f))
+                .collect(),
+        }
+    }
 }
 
-impl Default for Stack {
+impl<Message> Default for Stack<Message> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-#[typetag::serde]
-impl Widget for Stack {
+impl<Message> Widget<Message> for Stack<Message> {
     fn size_hint(&self) -> Size<Length> {
         Size::new(self.width, self.height)
     }
 
-    fn data(&self) -> Vec<u8> {
-        bincode::serde::encode_to_vec(self, bincode::config::standard()).unwrap()
+    fn children(&self) -> Vec<&Element<Message>> {
+        self.children.iter().collect()
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut Element<Message>> {
+        self.children.iter_mut().collect()
+    }
+
+    fn child_list(&mut self) -> Option<&mut Vec<Element<Message>>> {
+        Some(&mut self.children)
     }
 }
 
-impl From<Stack> for Element {
-    fn from(stack: Stack) -> Self {
-        Self::new(Box::new(stack))
+impl<Message> From<Stack<Message>> for Element<Message> {
+    fn from(stack: Stack<Message>) -> Self {
+        Self::Stack(stack)
     }
 }
 
-impl<'a> From<Stack> for iced::Element<'a , PluginRequest, iced::Theme, iced::Renderer>
+impl<'a> From<&'a Stack> for iced::Element<'a , PluginRequest, iced::Theme, iced::Renderer>
 
 {
-    fn from(value: Stack) -> Self {
-        iced::widget::Stack::from_vec(value.children.into_iter().map(|e| to_element(&e)).collect())
+    fn from(value: &'a Stack) -> Self {
+        iced::widget::Stack::from_vec(value.children.iter().map(to_element).collect())
             .width(value.width)
             .height(value.height)
             .into()
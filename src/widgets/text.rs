@@ -78,7 +78,7 @@ pub struct Text {
     shaping: Shaping,
     #[serde(with = "WrappingDef")]
     wrapping: Wrapping,
-    class: Option<Style>,
+    class: Class,
 }
 
 impl Text {
@@ -95,7 +95,7 @@ impl Text {
             vertical_alignment: alignment::Vertical::Top,
             shaping: Shaping::default(),
             wrapping: Wrapping::default(),
-            class: None,
+            class: Class::default(),
         }
     }
 
@@ -164,7 +164,7 @@ impl Text {
     /// Sets the style of the [`Text`].
     #[must_use]
     pub fn style(mut self, style: Style) -> Self {
-        self.class = Some(style);
+        self.class = Class::Style(Box::new(style));
         self
     }
 
@@ -181,33 +181,32 @@ impl Text {
     }
 
     /// Sets the style class of the [`Text`].
+    ///
+    /// Classes other than [`Class::Style`] are resolved against the theme of
+    /// the host.
     #[must_use]
-    pub fn class(mut self, class: Style) -> Self {
+    pub fn class(mut self, class: impl Into<Class>) -> Self {
         self.class = class.into();
         self
     }
 }
 
-#[typetag::serde]
-impl Widget for Text {
+impl<Message> Widget<Message> for Text {
     fn size_hint(&self) -> iced::Size<iced::Length> {
         Size::new(self.width, self.height)
     }
-
-    fn data(&self) -> Vec<u8> {
-        bincode::serde::encode_to_vec(self, bincode::config::standard()).unwrap()
-    }
 }
 
-impl From<Text> for Element {
-    fn from(value: Text) -> Element {
-        Element::new(Box::new(value))
+impl<Message> From<Text> for Element<Message> {
+    fn from(value: Text) -> Element<Message> {
+        Element::Text(Box::new(value))
     }
 }
 
-impl<'a> From<Text> for iced::Element<'a, PluginRequest, iced::Theme, iced::Renderer> {
-    fn from(value: Text) -> iced::Element<'a, PluginRequest, Theme, iced::Renderer> {
-        let mut text = iced::widget::Text::new(value.fragment)
+impl<'a> From<&'a Text> for iced::Element<'a, PluginRequest, iced::Theme, iced::Renderer> {
+    fn from(value: &'a Text) -> iced::Element<'a, PluginRequest, Theme, iced::Renderer> {
+        let class = value.class.clone();
+        let mut text = iced::widget::Text::new(&value.fragment)
             .align_x(value.horizontal_alignment)
             .align_y(value.vertical_alignment)
             .shaping(value.shaping)
@@ -215,12 +214,8 @@ impl<'a> From<Text> for iced::Element<'a, PluginRequest, iced::Theme, iced::Rend
             .width(value.width)
             .height(value.height)
             .wrapping(value.wrapping)
-            .style(move |theme| if let Some(class) = value.class {
-                class.into()
-            } else {
-                iced::widget::text::default(theme)
-            });
-        if let Some(font) = value.font {
+            .style(move |theme| class.resolve(theme).into());
+        if let Some(font) = value.font.clone() {
             text = text.font(font)
         }
         if let Some(size) = value.size {
@@ -236,14 +231,55 @@ impl<'a> From<&'a str> for Text {
     }
 }
 
-impl<'a> From<&'a str> for Element {
+impl<'a, Message> From<&'a str> for Element<Message> {
     fn from(content: &'a str) -> Self {
         Text::from(content).into()
     }
 }
 
+/// The style class of a [`Text`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum Class {
+    /// See [`default`].
+    #[default]
+    Default,
+    /// See [`base`].
+    Base,
+    /// See [`primary`].
+    Primary,
+    /// See [`secondary`].
+    Secondary,
+    /// See [`success`].
+    Success,
+    /// See [`danger`].
+    Danger,
+    /// A fixed style, independent of the theme.
+    Style(Box<Style>),
+}
+
+impl Class {
+    /// The [`Style`] of the class in the given theme.
+    pub fn resolve(&self, theme: &Theme) -> Style {
+        match self {
+            Class::Default => default(theme),
+            Class::Base => base(theme),
+            Class::Primary => primary(theme),
+            Class::Secondary => secondary(theme),
+            Class::Success => success(theme),
+            Class::Danger => danger(theme),
+            Class::Style(style) => **style,
+        }
+    }
+}
+
+impl From<Style> for Class {
+    fn from(style: Style) -> Self {
+        Class::Style(Box::new(style))
+    }
+}
+
 /// The appearance of some text.
-#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
 pub struct Style {
     /// The [`Color`] of the text.
     ///
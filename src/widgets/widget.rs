@@ -1,10 +1,22 @@
-use std::fmt::Debug;
-
 use iced::{Length, Size};
 
-#[typetag::serde(tag = "type")]
-pub trait Widget: Debug + Sync + Send {
+use crate::Element;
+
+pub trait Widget<Message = Vec<u8>> {
     fn size_hint(&self) -> Size<Length>;
 
-    fn data(&self) -> Vec<u8>;
+    /// The elements nested inside of the widget.
+    fn children(&self) -> Vec<&Element<Message>> {
+        Vec::new()
+    }
+
+    /// Mutable access to the elements nested inside of the widget.
+    fn children_mut(&mut self) -> Vec<&mut Element<Message>> {
+        Vec::new()
+    }
+
+    /// The list of children, for widgets that can hold any number of them.
+    fn child_list(&mut self) -> Option<&mut Vec<Element<Message>>> {
+        None
+    }
 }